@@ -1,14 +1,44 @@
 use core::marker::PhantomData;
 
-pub struct Client<T> {
+use syscall::abi::Lease;
+
+pub struct Client<'a, T> {
     task_id: u8,
+    timeout: u32,
+    leases: &'a [Lease],
     phantom: PhantomData<T>,
 }
 
-impl<T> Client<T> {
+impl<T> Client<'static, T> {
     pub fn new(task_id: u8) -> Self {
         Client {
             task_id,
+            timeout: 0,
+            leases: &[],
+            phantom: PhantomData,
+        }
+    }
+
+    // Create a client where every call fails with CallStatus::Timeout if the
+    // server has not replied within timeout microseconds.
+    pub fn with_timeout(task_id: u8, timeout: u32) -> Self {
+        Client {
+            task_id,
+            timeout,
+            leases: &[],
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Client<'a, T> {
+    // Create a client where every call lends the memory described by leases
+    // to the server, which may access it until it replies.
+    pub fn with_leases(task_id: u8, leases: &'a [Lease]) -> Self {
+        Client {
+            task_id,
+            timeout: 0,
+            leases,
             phantom: PhantomData,
         }
     }
@@ -16,4 +46,12 @@ impl<T> Client<T> {
     pub fn task_id(&self) -> u8 {
         self.task_id
     }
+
+    pub fn timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    pub fn leases(&self) -> &'a [Lease] {
+        self.leases
+    }
 }
@@ -86,7 +86,11 @@ macro_rules! rpc_interface {
                 _pad: [0; $fn_name::InputOuter::NUM_PADDING_BYTES],
             };
 
-            let $crate::macro_util::syscall::CallResult { data, len: out_len } = $crate::macro_util::syscall::sys_call::<IN_LEN, OUT_LEN>(self.task_id(), IN_LEN as u8, in_message.into_data());
+            let $crate::macro_util::syscall::CallResult { status, data, len: out_len } = $crate::macro_util::syscall::sys_call_leases::<IN_LEN, OUT_LEN>(self.task_id(), IN_LEN as u8, in_message.into_data(), self.leases(), self.timeout());
+            if (status != $crate::macro_util::syscall::abi::IpcStatus::Success) {
+                return Err(status.into());
+            }
+
             let out_len = out_len as usize;
 
             if (out_len < OutputHeader::LEN) {
@@ -234,7 +238,7 @@ macro_rules! rpc_interface {
                     }
                 }
 
-                impl super::$iface_name<$crate::CallStatus> for super::Client {
+                impl super::$iface_name<$crate::CallStatus> for super::Client<'_> {
                     $($crate::rpc_interface! { @client_fn $fn_name( $($args)* , ) -> $ret } )+
                 }
             }
@@ -254,7 +258,7 @@ macro_rules! rpc_interface {
 
         #[doc(hidden)]
         pub enum Interface { }
-        pub type Client = $crate::Client<Interface>;
+        pub type Client<'a> = $crate::Client<'a, Interface>;
 
         $(#[$outer])*
         $v trait $iface_name<T> {
@@ -9,8 +9,7 @@ pub use server::{Dispatch, Notify, Server};
 
 #[open_enum::open_enum]
 #[repr(u8)]
-#[derive(Debug)]
-#[derive(Clone, Copy, zerocopy::AsBytes, zerocopy::FromBytes, zerocopy::FromZeroes)]
+#[derive(Debug, Clone, Copy, zerocopy::AsBytes, zerocopy::FromBytes, zerocopy::FromZeroes)]
 pub enum CallStatus {
     Success,
     InvalidCallCode,
@@ -18,6 +17,22 @@ pub enum CallStatus {
     InvalidOutput,
     InvalidParameter,
     OperationFailed,
+    Timeout,
+    InvalidTarget,
+    TargetDead,
+    AccessDenied,
+}
+
+impl From<syscall::abi::IpcStatus> for CallStatus {
+    fn from(status: syscall::abi::IpcStatus) -> Self {
+        match status {
+            syscall::abi::IpcStatus::Timeout => CallStatus::Timeout,
+            syscall::abi::IpcStatus::InvalidTarget => CallStatus::InvalidTarget,
+            syscall::abi::IpcStatus::TargetDead => CallStatus::TargetDead,
+            syscall::abi::IpcStatus::AccessDenied => CallStatus::AccessDenied,
+            _ => CallStatus::OperationFailed,
+        }
+    }
 }
 
 #[doc(hidden)]
@@ -0,0 +1,93 @@
+use kernel_types::syscall::abi;
+
+pub struct LeaseInfo {
+    pub access: abi::LeaseAccess,
+    pub len: usize,
+}
+
+#[inline(always)]
+pub fn sys_borrow_info(lender: u8, lease: u8) -> Result<LeaseInfo, abi::IpcStatus> {
+    let params = (lender as u32) | ((lease as u32) << 8);
+    let out_params: u32;
+    let len: usize;
+
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::BorrowInfo.0,
+            inlateout("a1") params => out_params,
+            lateout("a2") len,
+            options(nomem, nostack),
+        )
+    }
+
+    let status = abi::IpcStatus(out_params as u8);
+    if status != abi::IpcStatus::Success {
+        return Err(status);
+    }
+
+    let access = abi::LeaseAccess((out_params >> 8) as u8);
+    Ok(LeaseInfo { access, len })
+}
+
+#[inline(always)]
+pub fn sys_borrow_read(
+    lender: u8,
+    lease: u8,
+    offset: usize,
+    buffer: &mut [u8],
+) -> Result<usize, abi::IpcStatus> {
+    let params = (lender as u32) | ((lease as u32) << 8);
+    let out_params: u32;
+    let len: usize;
+
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::BorrowRead.0,
+            inlateout("a1") params => out_params,
+            inlateout("a2") offset => len,
+            in("a3") buffer.as_mut_ptr(),
+            in("a4") buffer.len(),
+            options(nostack),
+        )
+    }
+
+    let status = abi::IpcStatus(out_params as u8);
+    if status != abi::IpcStatus::Success {
+        return Err(status);
+    }
+
+    Ok(len)
+}
+
+#[inline(always)]
+pub fn sys_borrow_write(
+    lender: u8,
+    lease: u8,
+    offset: usize,
+    buffer: &[u8],
+) -> Result<usize, abi::IpcStatus> {
+    let params = (lender as u32) | ((lease as u32) << 8);
+    let out_params: u32;
+    let len: usize;
+
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::BorrowWrite.0,
+            inlateout("a1") params => out_params,
+            inlateout("a2") offset => len,
+            in("a3") buffer.as_ptr(),
+            in("a4") buffer.len(),
+            options(readonly, nostack),
+        )
+    }
+
+    let status = abi::IpcStatus(out_params as u8);
+    if status != abi::IpcStatus::Success {
+        return Err(status);
+    }
+
+    Ok(len)
+}
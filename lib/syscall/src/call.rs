@@ -3,6 +3,7 @@ use core::mem::size_of;
 use kernel_types::syscall::abi;
 
 pub struct CallResult<const OUT_SIZE: usize> {
+    pub status: abi::IpcStatus,
     pub len: u8,
     pub data: [usize; OUT_SIZE],
 }
@@ -12,15 +13,70 @@ pub fn sys_call<const IN_SIZE: usize, const OUT_SIZE: usize>(
     target: u8,
     in_len: u8,
     input: [usize; IN_SIZE],
+) -> CallResult<OUT_SIZE> {
+    call(abi::SysCallId::Call, target, in_len, input, &[], 0)
+}
+
+#[inline(always)]
+pub fn sys_call_timeout<const IN_SIZE: usize, const OUT_SIZE: usize>(
+    target: u8,
+    in_len: u8,
+    input: [usize; IN_SIZE],
+    timeout: u32,
+) -> CallResult<OUT_SIZE> {
+    call(
+        abi::SysCallId::CallTimeout,
+        target,
+        in_len,
+        input,
+        &[],
+        timeout,
+    )
+}
+
+#[inline(always)]
+pub fn sys_call_leases<const IN_SIZE: usize, const OUT_SIZE: usize>(
+    target: u8,
+    in_len: u8,
+    input: [usize; IN_SIZE],
+    leases: &[abi::Lease],
+    timeout: u32,
+) -> CallResult<OUT_SIZE> {
+    call(
+        abi::SysCallId::CallTimeout,
+        target,
+        in_len,
+        input,
+        leases,
+        timeout,
+    )
+}
+
+// SYS_CALL ignores the timeout, and the lease table is only read when leases
+// are attached.
+#[inline(always)]
+fn call<const IN_SIZE: usize, const OUT_SIZE: usize>(
+    id: abi::SysCallId,
+    target: u8,
+    in_len: u8,
+    input: [usize; IN_SIZE],
+    leases: &[abi::Lease],
+    timeout: u32,
 ) -> CallResult<OUT_SIZE> {
     assert!((in_len as usize) <= size_of::<usize>() * IN_SIZE);
+    assert!(leases.len() <= u8::MAX as usize);
 
     let out_size = (OUT_SIZE * size_of::<usize>()) as u8;
-    let params = (target as u32) | ((in_len as u32) << 16) | ((out_size as u32) << 24);
+    let params = (target as u32)
+        | ((leases.len() as u32) << 8)
+        | ((in_len as u32) << 16)
+        | ((out_size as u32) << 24);
 
     let mut output: core::mem::MaybeUninit<[usize; OUT_SIZE]> = core::mem::MaybeUninit::uninit();
-    let out_len: u32;
+    let out_params: u32;
 
+    // The target may access any leased memory during the call, so this can't
+    // be marked nomem.
     let data = unsafe {
         let output_mut = output.assume_init_mut();
 
@@ -29,15 +85,18 @@ pub fn sys_call<const IN_SIZE: usize, const OUT_SIZE: usize>(
         OUT_SIZE,
         input,
         output_mut,
-        in("a0") abi::SysCallId::Call.0,
+        in("a0") id.0,
         in("a1") params,
-        lateout("a1") out_len,
-        options(nomem, nostack),
+        in("a2") timeout,
+        in("s5") leases.as_ptr(),
+        lateout("a1") out_params,
+        options(nostack),
         );
 
         output.assume_init()
     };
 
-    let len = out_len as u8;
-    CallResult { len, data }
+    let len = (out_params & 0xff) as u8;
+    let status = abi::IpcStatus((out_params >> 8) as u8);
+    CallResult { status, len, data }
 }
@@ -0,0 +1,17 @@
+use kernel_types::syscall::abi;
+
+#[inline(always)]
+pub fn sys_faulted_tasks() -> u32 {
+    let tasks: u32;
+
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::FaultedTasks.0,
+            lateout("a1") tasks,
+            options(nomem, nostack),
+        )
+    }
+
+    tasks
+}
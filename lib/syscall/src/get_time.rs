@@ -0,0 +1,19 @@
+use kernel_types::syscall::abi;
+
+#[inline(always)]
+pub fn sys_get_time() -> u64 {
+    let now_low: u32;
+    let now_high: u32;
+
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::GetTime.0,
+            lateout("a1") now_low,
+            lateout("a2") now_high,
+            options(nomem, nostack),
+        )
+    }
+
+    ((now_high as u64) << 32) | (now_low as u64)
+}
@@ -0,0 +1,12 @@
+use kernel_types::syscall::abi;
+
+#[inline(always)]
+pub fn sys_heartbeat() {
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::Heartbeat.0,
+            options(nomem, nostack),
+        )
+    }
+}
@@ -1,22 +1,42 @@
 #![feature(asm_const)]
 #![no_std]
 
+mod borrow;
 mod call;
+mod faulted_tasks;
+mod get_time;
+mod heartbeat;
 mod interrupt_control;
 mod notify;
 mod panic;
+mod read_crash;
+mod read_fault;
 mod receive;
+mod restart_task;
 mod send;
 mod set_timer;
+mod task_info;
+mod timer_missed;
+mod yield_now;
 
-pub use call::{sys_call, CallResult};
+pub use borrow::{sys_borrow_info, sys_borrow_read, sys_borrow_write, LeaseInfo};
+pub use call::{sys_call, sys_call_leases, sys_call_timeout, CallResult};
+pub use faulted_tasks::sys_faulted_tasks;
+pub use get_time::sys_get_time;
+pub use heartbeat::sys_heartbeat;
 pub use interrupt_control::{sys_interrupt_control, InterruptControl};
 pub use kernel_types::syscall::abi;
 pub use notify::sys_notify;
 pub use panic::sys_panic;
+pub use read_crash::{sys_read_crash, CrashKind, CrashRecord};
+pub use read_fault::{sys_read_fault, FaultRecord};
 pub use receive::{sys_receive, ReceiveResult};
+pub use restart_task::sys_restart_task;
 pub use send::sys_send;
-pub use set_timer::sys_set_timer;
+pub use set_timer::{sys_set_timer, sys_set_timer_absolute};
+pub use task_info::{sys_task_info, TaskInfo};
+pub use timer_missed::sys_timer_missed;
+pub use yield_now::sys_yield;
 
 macro_rules! syscall {
     (@asm ($($regs:tt)*)) => {
@@ -1,14 +1,18 @@
 use kernel_types::syscall::abi;
 
 #[inline(always)]
-pub fn sys_notify(target: u8, notifications: u32) {
+pub fn sys_notify(target: u8, notifications: u32) -> abi::IpcStatus {
+    let status: u32;
+
     unsafe {
         core::arch::asm!(
             "ecall",
             in("a0") abi::SysCallId::Notify.0,
-            in("a1") target,
+            inlateout("a1") target as u32 => status,
             in("a2") notifications,
             options(nomem, nostack),
         )
     }
+
+    abi::IpcStatus(status as u8)
 }
@@ -0,0 +1,33 @@
+use core::mem::MaybeUninit;
+
+pub use kernel_types::arch::{CrashKind, CrashRecord};
+use kernel_types::syscall::abi;
+
+// Take the record of the last crash before a reset, if there is one.
+#[inline(always)]
+pub fn sys_read_crash() -> Result<Option<CrashRecord>, abi::IpcStatus> {
+    let mut record = MaybeUninit::<CrashRecord>::uninit();
+    let result: u32;
+
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::ReadCrash.0,
+            in("a1") record.as_mut_ptr(),
+            lateout("a1") result,
+            options(nostack),
+        )
+    }
+
+    let status = abi::IpcStatus(result as u8);
+    if status != abi::IpcStatus::Success {
+        return Err(status);
+    }
+
+    if (result >> 8) & 0xff != 0 {
+        // Safety: The kernel has written a valid record.
+        Ok(Some(unsafe { record.assume_init() }))
+    } else {
+        Ok(None)
+    }
+}
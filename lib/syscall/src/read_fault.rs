@@ -0,0 +1,36 @@
+pub use kernel_types::arch::FaultRecord;
+use kernel_types::syscall::abi;
+
+#[inline(always)]
+pub fn sys_read_fault(target: u8) -> Option<FaultRecord> {
+    let valid: u32;
+    let mcause: usize;
+    let mtval: usize;
+    let pc: usize;
+    let sp: usize;
+
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::ReadFault.0,
+            in("a1") target,
+            lateout("a1") valid,
+            lateout("a2") mcause,
+            lateout("a3") mtval,
+            lateout("a4") pc,
+            lateout("a5") sp,
+            options(nomem, nostack),
+        )
+    }
+
+    if (valid & 0xff) != 0 {
+        Some(FaultRecord {
+            mcause,
+            mtval,
+            pc,
+            sp,
+        })
+    } else {
+        None
+    }
+}
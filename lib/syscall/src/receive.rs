@@ -5,7 +5,12 @@ use kernel_types::syscall::abi;
 pub struct ReceiveResult<const OUT_SIZE: usize> {
     pub notifications: u32,
     pub sender: u8,
+    pub leases: u8,
     pub len: u8,
+    // The number of periods of a periodic timer in slot 0 that expired since
+    // the last receive in addition to the one being notified. Only slot 0 is
+    // reported here, other slots are read with sys_timer_missed.
+    pub missed_periods: u8,
     pub data: [usize; OUT_SIZE],
 }
 
@@ -38,12 +43,16 @@ pub fn sys_receive<const OUT_SIZE: usize>() -> ReceiveResult<OUT_SIZE> {
     };
 
     let sender = (out_params & 0xff) as u8;
+    let leases = (out_params >> 8) as u8;
     let len = (out_params >> 16) as u8;
+    let missed_periods = (out_params >> 24) as u8;
 
     ReceiveResult {
         notifications,
         sender,
+        leases,
         len,
+        missed_periods,
         data,
     }
 }
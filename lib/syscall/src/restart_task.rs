@@ -0,0 +1,16 @@
+use kernel_types::syscall::abi;
+
+#[inline(always)]
+pub fn sys_restart_task(target: u8) -> bool {
+    let restarted: u32;
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::RestartTask.0,
+            in("a1") target,
+            lateout("a1") restarted,
+            options(nomem, nostack),
+        )
+    }
+    (restarted & 0xff) != 0
+}
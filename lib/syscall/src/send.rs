@@ -3,10 +3,15 @@ use core::mem::size_of;
 use kernel_types::syscall::abi;
 
 #[inline(always)]
-pub fn sys_send<const IN_SIZE: usize>(target: u8, len: u8, data: [usize; IN_SIZE]) {
+pub fn sys_send<const IN_SIZE: usize>(
+    target: u8,
+    len: u8,
+    data: [usize; IN_SIZE],
+) -> abi::IpcStatus {
     assert!((len as usize) <= size_of::<usize>() * IN_SIZE);
 
     let params = (target as u32) | ((len as u32) << 16);
+    let status: u32;
 
     unsafe {
         let mut empty_out = [0usize; 0];
@@ -18,7 +23,10 @@ pub fn sys_send<const IN_SIZE: usize>(target: u8, len: u8, data: [usize; IN_SIZE
         empty_out,
         in("a0") abi::SysCallId::Send.0,
         in("a1") params,
+        lateout("a1") status,
         options(nomem, nostack),
         );
     }
+
+    abi::IpcStatus(status as u8)
 }
@@ -1,15 +1,29 @@
 use kernel_types::syscall::abi;
 
 #[inline(always)]
-pub fn sys_set_timer(periodic: bool, deadline: u32) {
+pub fn sys_set_timer(slot: u8, periodic: bool, deadline: u32) {
     let periodic = if periodic { 1u8 } else { 0u8 };
     unsafe {
         core::arch::asm!(
             "ecall",
             in("a0") abi::SysCallId::SetTimer.0,
-            in("a1") periodic,
+            in("a1") (periodic as u32) | ((slot as u32) << 8),
             in("a2") deadline,
             options(nomem, nostack),
         )
     }
 }
+
+#[inline(always)]
+pub fn sys_set_timer_absolute(slot: u8, deadline: u64) {
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::SetTimerAbsolute.0,
+            in("a1") deadline as u32,
+            in("a2") (deadline >> 32) as u32,
+            in("a3") slot as u32,
+            options(nomem, nostack),
+        )
+    }
+}
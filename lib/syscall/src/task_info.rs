@@ -0,0 +1,102 @@
+use core::mem::size_of;
+
+use kernel_types::{syscall::abi, task::MAX_TASK_NAME_LENGTH};
+
+pub struct TaskInfo {
+    pub state: abi::TaskState,
+    // The rank of the task's current priority among the priorities configured
+    // in app.toml, 0 being the highest. This includes any priority inherited
+    // from tasks blocked in a SYS_CALL to it, and is not the configured value.
+    pub priority: u8,
+    // The target of a pending SYS_CALL.
+    pub blocked_on: Option<u8>,
+    pub notifications: u32,
+    // The earliest timer deadline in microseconds since boot.
+    pub timer_deadline: Option<u64>,
+    pub stack_size: u32,
+    // The stack high-water mark in bytes, this is only meaningful if the task
+    // paints its stack with rtos_task_entry(paint_stack).
+    pub stack_used: u32,
+    name: [u8; MAX_TASK_NAME_LENGTH],
+}
+
+impl TaskInfo {
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(MAX_TASK_NAME_LENGTH);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+}
+
+#[inline(always)]
+pub fn sys_task_info(target: u8) -> Result<TaskInfo, abi::IpcStatus> {
+    let params: u32;
+    let notifications: u32;
+    let deadline_low: u32;
+    let deadline_high: u32;
+    let stack_size: u32;
+    let stack_used: u32;
+    // The name is returned in as many registers as it needs, which depends on
+    // XLEN, the remaining registers are unused.
+    let name: [usize; 4];
+
+    unsafe {
+        let name0: usize;
+        let name1: usize;
+        let name2: usize;
+        let name3: usize;
+
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::TaskInfo.0,
+            in("a1") target as u32,
+            lateout("a1") params,
+            lateout("a2") notifications,
+            lateout("a3") deadline_low,
+            lateout("a4") deadline_high,
+            lateout("a5") stack_size,
+            lateout("a6") stack_used,
+            lateout("a7") name0,
+            lateout("t0") name1,
+            lateout("t1") name2,
+            lateout("t2") name3,
+            options(nomem, nostack),
+        );
+
+        name = [name0, name1, name2, name3];
+    }
+
+    let status = abi::IpcStatus(params as u8);
+    if status != abi::IpcStatus::Success {
+        return Err(status);
+    }
+
+    let blocked_on = match (params >> 24) as u8 {
+        u8::MAX => None,
+        target => Some(target),
+    };
+
+    let timer_deadline = match ((deadline_high as u64) << 32) | (deadline_low as u64) {
+        u64::MAX => None,
+        deadline => Some(deadline),
+    };
+
+    let mut name_bytes = [0u8; MAX_TASK_NAME_LENGTH];
+    for (chunk, word) in name_bytes.chunks_exact_mut(size_of::<usize>()).zip(name) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+
+    Ok(TaskInfo {
+        state: abi::TaskState((params >> 8) as u8),
+        priority: (params >> 16) as u8,
+        blocked_on,
+        notifications,
+        timer_deadline,
+        stack_size,
+        stack_used,
+        name: name_bytes,
+    })
+}
@@ -0,0 +1,20 @@
+use kernel_types::syscall::abi;
+
+// Take the number of periods of a periodic timer in the given slot that
+// expired since the count was last taken, in addition to those notified. The
+// count of slot 0 is also taken by sys_receive, as ReceiveResult::missed_periods.
+#[inline(always)]
+pub fn sys_timer_missed(slot: u8) -> u8 {
+    let missed_periods: u32;
+
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a0") abi::SysCallId::TimerMissed.0,
+            inlateout("a1") slot as u32 => missed_periods,
+            options(nomem, nostack),
+        )
+    }
+
+    missed_periods as u8
+}
@@ -5,5 +5,46 @@ rpc::rpc_interface! {
         fn set_timer(periodic: u8, #[padding] _pad: [u8; 3], deadline: u32) -> ();
         fn notification_count(bit: usize) -> u32;
         fn swap_buffer(buffer: [u8; 36]) -> [u8; 36];
+        fn sleep(duration: u32) -> ();
+        fn notify(task_id: u8, #[padding] _pad: [u8; 3], notification: u32) -> u8;
+        fn spin(heartbeat: u8, #[padding] _pad: [u8; 3], duration: u32) -> ();
+        fn fpu_swap(value: u32) -> u32;
+        fn borrow_len(lease: u8) -> u32;
+        fn borrow_read(lease: u8, #[padding] _pad: [u8; 3], offset: u32) -> u8;
+        fn borrow_write(lease: u8, #[padding] _pad: [u8; 3], offset: u32) -> u8;
+        fn borrow_read_boundary(lease: u8) -> u8;
+        fn restart_task(task_id: u8) -> u8;
+        fn call(task_id: u8) -> u8;
     }
 }
+
+// Used by test_runner and test_helper to check that FP state is kept across
+// task switches. The tasks are built without hardware floating-point, so the
+// compiler never uses the FP registers and f0 keeps its value between these.
+pub fn read_f0() -> u32 {
+    let value: u32;
+    unsafe {
+        core::arch::asm!(
+            ".option push",
+            ".option arch, +f",
+            "fmv.x.w {0}, f0",
+            ".option pop",
+            out(reg) value,
+            options(nomem, nostack),
+        )
+    };
+    value
+}
+
+pub fn write_f0(value: u32) {
+    unsafe {
+        core::arch::asm!(
+            ".option push",
+            ".option arch, +f",
+            "fmv.w.x f0, {0}",
+            ".option pop",
+            in(reg) value,
+            options(nomem, nostack),
+        )
+    };
+}
@@ -1,6 +1,8 @@
 use pac_riscv::aclint::{Mswi, MtimerCompare, MtimerTime};
 use rtos_macros::rtos_import;
 
+use super::{hart_id, BOOT_HART};
+
 #[rtos_import]
 pub static mut PERIPHERAL_ACLINT_MSWI_BASE: u32;
 
@@ -14,14 +16,30 @@ pub fn aclint_init() {
     clear_software_interrupt()
 }
 
+// Deadlines are only handled by the boot hart, so the comparator of any other
+// hart is disabled.
+pub fn aclint_hart_init() {
+    let mtimer_compare = unsafe {
+        MtimerCompare::from_ptr(&mut PERIPHERAL_ACLINT_MTIMER_COMPARE_BASE as *mut _ as *mut _)
+    };
+    mtimer_compare.mtimecmp(hart_id()).write_value(u64::MAX);
+
+    clear_software_interrupt()
+}
+
 pub fn set_software_interrupt() {
+    send_software_interrupt(hart_id());
+}
+
+// Set the software interrupt of any hart, including this one.
+pub fn send_software_interrupt(hart: usize) {
     let mswi = unsafe { Mswi::from_ptr(&mut PERIPHERAL_ACLINT_MSWI_BASE as *mut _ as *mut _) };
-    mswi.msip(0).write(|x| x.set_pending(true));
+    mswi.msip(hart).write(|x| x.set_pending(true));
 }
 
 pub fn clear_software_interrupt() {
     let mswi = unsafe { Mswi::from_ptr(&mut PERIPHERAL_ACLINT_MSWI_BASE as *mut _ as *mut _) };
-    mswi.msip(0).write(|x| x.set_pending(false));
+    mswi.msip(hart_id()).write(|x| x.set_pending(false));
 }
 
 pub fn now_ticks() -> u64 {
@@ -44,14 +62,14 @@ pub fn set_timer_deadline(ticks: u64) {
     let mtimer_compare = unsafe {
         MtimerCompare::from_ptr(&mut PERIPHERAL_ACLINT_MTIMER_COMPARE_BASE as *mut _ as *mut _)
     };
-    mtimer_compare.mtimecmp(0).write_value(ticks);
+    mtimer_compare.mtimecmp(BOOT_HART).write_value(ticks);
 }
 
 pub fn timer_deadline() -> u64 {
     let mtimer_compare = unsafe {
         MtimerCompare::from_ptr(&mut PERIPHERAL_ACLINT_MTIMER_COMPARE_BASE as *mut _ as *mut _)
     };
-    mtimer_compare.mtimecmp(0).read()
+    mtimer_compare.mtimecmp(BOOT_HART).read()
 }
 
 pub fn clear_timer_interrupt() {
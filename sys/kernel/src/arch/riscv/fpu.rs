@@ -0,0 +1,191 @@
+// Lazy switching of the F/D register file. While a task runs without its FP
+// state loaded mstatus.FS is Off, so its first FP instruction raises an
+// illegal instruction exception. A task with Flags::FPU then has its state
+// restored and the instruction is retried, any other task is faulted. When a
+// task stops running on a hart its state is only saved if mstatus.FS shows
+// that it has modified the registers since they were restored.
+//
+// The kernel itself never uses the FP registers, so they still hold the
+// task's state when a trap is handled.
+
+use kernel_types::task::Flags;
+use riscv::register::mstatus::{self, FS};
+
+use crate::task;
+
+// The task whose state is held in the FP registers of each hart, while
+// mstatus.FS is not Off.
+static mut LOADED: [Option<task::TaskId>; super::NUM_HARTS] = [None; super::NUM_HARTS];
+
+#[repr(C)]
+pub struct FpuContext {
+    f: [u64; 32],
+    fcsr: usize,
+}
+
+impl FpuContext {
+    pub const fn zeroed() -> Self {
+        Self {
+            f: [0; 32],
+            fcsr: 0,
+        }
+    }
+}
+
+pub fn fpu_hart_init() {
+    // Safety: No task is running, so there is no FP state to lose.
+    unsafe { mstatus::set_fs(FS::Off) };
+}
+
+// Called when a task that was running on this hart will not be resumed
+// immediately, whether another task is run or the hart idles.
+pub fn switch_out(task: &mut task::Task) {
+    if let FS::Dirty = mstatus::read().fs() {
+        save(task.fpu_context_mut());
+    }
+
+    // Safety: We hold a mutable reference to a task, so TASK_TABLE_LOCK must be
+    // held and nothing else may be accessing LOADED.
+    unsafe { LOADED[super::hart_id()] = None };
+
+    // Safety: The state of the task is saved, if it was modified, so the next
+    // task to use the FPU will trap and have its own state restored.
+    unsafe { mstatus::set_fs(FS::Off) };
+}
+
+// Handle an illegal instruction exception, returning true if it was the first
+// use of the FPU by a task permitted to use it, in which case the instruction
+// should be retried.
+pub fn handle_first_use(task: &task::Task) -> bool {
+    if !matches!(mstatus::read().fs(), FS::Off) || !task.descriptor().flags.contains(Flags::FPU) {
+        return false;
+    }
+
+    restore(task.fpu_context());
+
+    // Safety: The registers now hold the state of the task. We hold a
+    // reference to a task, so TASK_TABLE_LOCK must be held and nothing else
+    // may be accessing LOADED.
+    unsafe {
+        mstatus::set_fs(FS::Clean);
+        LOADED[super::hart_id()] = Some(task.index());
+    }
+
+    true
+}
+
+// Called when a task is reset, so that it starts again from zeroed FP state. If
+// the state of the task is held in the registers of this hart it is discarded
+// without being saved.
+pub fn reset_fpu(task: &mut task::Task) {
+    *task.fpu_context_mut() = FpuContext::zeroed();
+
+    // Safety: We hold a mutable reference to a task, so TASK_TABLE_LOCK must be
+    // held and nothing else may be accessing LOADED. The registers hold the
+    // state of the task being reset, which is no longer needed.
+    unsafe {
+        let loaded = &mut LOADED[super::hart_id()];
+        if *loaded == Some(task.index()) {
+            *loaded = None;
+            mstatus::set_fs(FS::Off);
+        }
+    }
+}
+
+fn save(context: &mut FpuContext) {
+    // Safety: The FPU is enabled, as mstatus.FS is dirty, and the context is
+    // large enough for every register.
+    unsafe {
+        core::arch::asm!(
+            ".option push",
+            ".option arch, +d",
+            "fsd f0, 0({f})",
+            "fsd f1, 8({f})",
+            "fsd f2, 16({f})",
+            "fsd f3, 24({f})",
+            "fsd f4, 32({f})",
+            "fsd f5, 40({f})",
+            "fsd f6, 48({f})",
+            "fsd f7, 56({f})",
+            "fsd f8, 64({f})",
+            "fsd f9, 72({f})",
+            "fsd f10, 80({f})",
+            "fsd f11, 88({f})",
+            "fsd f12, 96({f})",
+            "fsd f13, 104({f})",
+            "fsd f14, 112({f})",
+            "fsd f15, 120({f})",
+            "fsd f16, 128({f})",
+            "fsd f17, 136({f})",
+            "fsd f18, 144({f})",
+            "fsd f19, 152({f})",
+            "fsd f20, 160({f})",
+            "fsd f21, 168({f})",
+            "fsd f22, 176({f})",
+            "fsd f23, 184({f})",
+            "fsd f24, 192({f})",
+            "fsd f25, 200({f})",
+            "fsd f26, 208({f})",
+            "fsd f27, 216({f})",
+            "fsd f28, 224({f})",
+            "fsd f29, 232({f})",
+            "fsd f30, 240({f})",
+            "fsd f31, 248({f})",
+            "frcsr {fcsr}",
+            ".option pop",
+            f = in(reg) context.f.as_mut_ptr(),
+            fcsr = out(reg) context.fcsr,
+            options(nostack),
+        );
+    }
+}
+
+fn restore(context: &FpuContext) {
+    // Safety: The FPU must be enabled before the registers are used, this is
+    // done as soon as they are restored.
+    unsafe {
+        mstatus::set_fs(FS::Initial);
+
+        core::arch::asm!(
+            ".option push",
+            ".option arch, +d",
+            "fld f0, 0({f})",
+            "fld f1, 8({f})",
+            "fld f2, 16({f})",
+            "fld f3, 24({f})",
+            "fld f4, 32({f})",
+            "fld f5, 40({f})",
+            "fld f6, 48({f})",
+            "fld f7, 56({f})",
+            "fld f8, 64({f})",
+            "fld f9, 72({f})",
+            "fld f10, 80({f})",
+            "fld f11, 88({f})",
+            "fld f12, 96({f})",
+            "fld f13, 104({f})",
+            "fld f14, 112({f})",
+            "fld f15, 120({f})",
+            "fld f16, 128({f})",
+            "fld f17, 136({f})",
+            "fld f18, 144({f})",
+            "fld f19, 152({f})",
+            "fld f20, 160({f})",
+            "fld f21, 168({f})",
+            "fld f22, 176({f})",
+            "fld f23, 184({f})",
+            "fld f24, 192({f})",
+            "fld f25, 200({f})",
+            "fld f26, 208({f})",
+            "fld f27, 216({f})",
+            "fld f28, 224({f})",
+            "fld f29, 232({f})",
+            "fld f30, 240({f})",
+            "fld f31, 248({f})",
+            "fscsr {fcsr}",
+            ".option pop",
+            f = in(reg) context.f.as_ptr(),
+            fcsr = in(reg) context.fcsr,
+            options(nostack, readonly),
+        );
+    }
+}
@@ -2,45 +2,174 @@ use core::{ffi::c_void, panic};
 
 pub use kernel_types::arch::riscv::*;
 use memoffset::offset_of;
+use paste::paste;
 use riscv::register;
 use rtos_macros::rtos_feature;
+use seq_macro::seq;
 use zerocopy::{AsBytes, FromBytes, FromZeroes, Ref};
 
-use crate::{init, syscall, task, time};
+use crate::{init, syscall, task, time, trace};
 
 #[cfg(feature = "riscv_aclint")]
 mod aclint;
+#[cfg(feature = "fpu")]
+mod fpu;
 #[cfg(feature = "riscv_plic")]
 mod plic;
+#[cfg(feature = "riscv_sifive_test")]
+mod sifive_test;
 #[cfg(feature = "riscv_wch_pfic")]
 mod wch_pfic;
 #[cfg(feature = "riscv_wch_systick")]
 mod wch_systick;
 
 #[cfg(feature = "riscv_aclint")]
-use aclint::{clear_software_interrupt, clear_timer_interrupt, set_software_interrupt};
+use aclint::{
+    clear_software_interrupt, clear_timer_interrupt, send_software_interrupt,
+    set_software_interrupt,
+};
 #[cfg(feature = "riscv_aclint")]
 pub use aclint::{now_ticks, set_timer_deadline, timer_deadline};
+#[cfg(feature = "fpu")]
+pub use fpu::{reset_fpu, FpuContext};
 #[cfg(feature = "riscv_plic")]
 use plic::handle_interrupt;
 #[cfg(feature = "riscv_plic")]
 pub use plic::{interrupt_control, reset_interrupt};
+#[cfg(feature = "riscv_sifive_test")]
+pub use sifive_test::system_reset;
 #[cfg(feature = "riscv_wch_pfic")]
 use wch_pfic::{
-    clear_software_interrupt, clear_timer_interrupt, handle_interrupt, set_software_interrupt,
+    clear_software_interrupt, clear_timer_interrupt, handle_interrupt, handle_pending_interrupts,
+    set_software_interrupt, sleep,
 };
 #[cfg(feature = "riscv_wch_pfic")]
-pub use wch_pfic::{interrupt_control, reset_interrupt};
+pub use wch_pfic::{interrupt_control, reset_interrupt, system_reset};
 #[cfg(feature = "riscv_wch_systick")]
 pub use wch_systick::{now_ticks, set_timer_deadline, timer_deadline};
 
 rtos_feature!("family_generic");
 rtos_feature!("family_wch_v4c");
+rtos_feature!("fpu");
+rtos_feature!("pmp_entries_8");
+rtos_feature!("pmp_entries_16");
+
+// Without either of the pmp_entries_N features the device has four PMP
+// entries.
+#[doc(hidden)]
+#[cfg(not(any(feature = "pmp_entries_8", feature = "pmp_entries_16")))]
+#[export_name = "rtos.feature.pmp_entries_4"]
+#[link_section = ".note.rtos.feature"]
+#[used]
+static RTOS_FEATURE_PMP_ENTRIES_DEFAULT_MARKER: () = ();
+
+seq!(N in 1..=4 {
+    paste! {
+        #[cfg(feature = "num_harts_" N)]
+        pub const NUM_HARTS: usize = N;
+        #[doc(hidden)]
+        #[cfg(feature = "num_harts_" N)]
+        #[export_name = "rtos.feature.num_harts_" N]
+        #[link_section = ".note.rtos.feature"]
+        #[used]
+        static [<RTOS_FEATURE_NUM_HARTS_ N _MARKER>]: () = ();
+    }
+});
+
+// Without any of the num_harts_N features the kernel runs on a single hart.
+#[cfg(not(feature = "num_harts_defined"))]
+pub const NUM_HARTS: usize = 1;
+#[doc(hidden)]
+#[cfg(not(feature = "num_harts_defined"))]
+#[export_name = "rtos.feature.num_harts_1"]
+#[link_section = ".note.rtos.feature"]
+#[used]
+static RTOS_FEATURE_NUM_HARTS_DEFAULT_MARKER: () = ();
+
+// Hart 0 is the boot hart, it initializes the kernel and is the only hart to
+// take timer and external interrupts. Other harts are only interrupted to
+// reschedule.
+pub const BOOT_HART: usize = 0;
+
+#[inline]
+pub fn hart_id() -> usize {
+    if NUM_HARTS == 1 {
+        BOOT_HART
+    } else {
+        register::mhartid::read()
+    }
+}
+
+// The top of this hart's kernel stack, the kernel stack is divided equally
+// between the harts.
+pub fn kernel_stack_top() -> usize {
+    extern "C" {
+        static _stack_start: c_void;
+        static _stack_end: c_void;
+    }
+
+    // Safety: Only the addresses of the linker symbols are taken.
+    let (start, end) = unsafe {
+        (
+            &_stack_start as *const _ as usize,
+            &_stack_end as *const _ as usize,
+        )
+    };
+
+    end - hart_id() * ((end - start) / NUM_HARTS)
+}
+
+// Set sp to the top of this hart's kernel stack, as kernel_stack_top. Clobbers
+// t0 and t1.
+macro_rules! hart_stack_asm {
+    () => {
+        concat!(
+            "la t0, {kernel_stack_start}\n",
+            "la sp, {kernel_top_of_stack}\n",
+            "sub t0, sp, t0\n",
+            "li t1, {num_harts}\n",
+            "divu t0, t0, t1\n",
+            "csrr t1, mhartid\n",
+            "mul t0, t0, t1\n",
+            "sub sp, sp, t0\n",
+        )
+    };
+}
+
+// Register sized loads and stores, so that the same assembly is used for both
+// RV32 and RV64.
+#[cfg(target_pointer_width = "32")]
+macro_rules! sx {
+    ($operands:literal) => {
+        concat!("sw ", $operands)
+    };
+}
+
+#[cfg(target_pointer_width = "32")]
+macro_rules! lx {
+    ($operands:literal) => {
+        concat!("lw ", $operands)
+    };
+}
+
+#[cfg(target_pointer_width = "64")]
+macro_rules! sx {
+    ($operands:literal) => {
+        concat!("sd ", $operands)
+    };
+}
+
+#[cfg(target_pointer_width = "64")]
+macro_rules! lx {
+    ($operands:literal) => {
+        concat!("ld ", $operands)
+    };
+}
 
 #[repr(C)]
 #[derive(AsBytes, FromBytes, FromZeroes)]
 pub struct SavedContext {
-    // Syscall in/out - 26 * sizeof(usize) = 104
+    // Syscall in/out - 26 registers
     // a0-a5, s0, s1 can be accessed with compressed instructions so we use
     // those for the smallest inputs
     a0: usize,
@@ -88,6 +217,10 @@ pub struct SavedContext {
     // reserved. We currently do not use them.
     gp: usize,
     tp: usize,
+
+    // The top of the kernel stack of the hart this task is running on, this
+    // is loaded by the trap vector.
+    kernel_sp: usize,
 }
 
 // We expect 26 syscall registers.
@@ -128,6 +261,7 @@ impl SavedContext {
             s9: 0,
             s10: 0,
             s11: 0,
+            kernel_sp: 0,
         }
     }
 
@@ -161,29 +295,193 @@ impl SavedContext {
     pub fn sys_advance_pc(&mut self) {
         self.pc += 4;
     }
+
+    pub fn fault_record(&self, cause: usize) -> FaultRecord {
+        FaultRecord {
+            mcause: cause,
+            mtval: register::mtval::read(),
+            pc: self.pc,
+            sp: self.sp,
+        }
+    }
+
+    // The general purpose registers, indexed by register number.
+    pub fn registers(&self) -> [usize; 32] {
+        [
+            0, self.ra, self.sp, self.gp, self.tp, self.t0, self.t1, self.t2, self.s0, self.s1,
+            self.a0, self.a1, self.a2, self.a3, self.a4, self.a5, self.a6, self.a7, self.s2,
+            self.s3, self.s4, self.s5, self.s6, self.s7, self.s8, self.s9, self.s10, self.s11,
+            self.t3, self.t4, self.t5, self.t6,
+        ]
+    }
+
+    // A record for a fault detected by the kernel rather than by the hardware,
+    // value is recorded in place of mtval.
+    pub fn kernel_fault_record(&self, cause: usize, value: usize) -> FaultRecord {
+        FaultRecord {
+            mcause: cause,
+            mtval: value,
+            pc: self.pc,
+            sp: self.sp,
+        }
+    }
 }
 
 #[inline]
 pub fn apply_memory_protection(task: &task::Task) {
     let pmp_addr = &task.descriptor().arch.pmp_addr;
-    let pmp_cfg = task.descriptor().arch.pmp_cfg;
+    let pmp_cfg = &task.descriptor().arch.pmp_cfg;
 
-    register::pmpcfg0::write(pmp_cfg as usize);
-    register::pmpaddr0::write(pmp_addr[0] as usize);
-    register::pmpaddr1::write(pmp_addr[1] as usize);
-    register::pmpaddr2::write(pmp_addr[2] as usize);
-    register::pmpaddr3::write(pmp_addr[3] as usize);
+    // Every entry the device implements is written, so that none are left
+    // over from the previous task.
+    seq!(N in 0..16 {
+        if let Some(&addr) = pmp_addr.get(N) {
+            register::pmpaddr~N::write(addr);
+        }
+    });
+
+    // On RV64 only the even numbered pmpcfg registers exist, each holding the
+    // configuration of eight entries.
+    #[cfg(target_pointer_width = "32")]
+    seq!(N in 0..4 {
+        if let Some(&cfg) = pmp_cfg.get(N) {
+            register::pmpcfg~N::write(cfg);
+        }
+    });
+    #[cfg(target_pointer_width = "64")]
+    {
+        register::pmpcfg0::write(pmp_cfg[0]);
+        if let Some(&cfg) = pmp_cfg.get(1) {
+            register::pmpcfg2::write(cfg);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read,
+    Write,
+}
+
+// Check that the memory protection configured for a task permits the given
+// access to every byte in base..base + len.
+pub fn can_access(task: &task::Task, base: usize, len: usize, access: MemoryAccess) -> bool {
+    const PMP_R: usize = 0x01;
+    const PMP_W: usize = 0x02;
+    const PMP_X: usize = 0x04;
+
+    let pmp_addr = &task.descriptor().arch.pmp_addr;
+    let pmp_cfg = &task.descriptor().arch.pmp_cfg;
+
+    // The base and length are supplied by a task, a range which wraps around
+    // the address space is never accessible.
+    let start = base as u64;
+    let end = match start.checked_add(len as u64) {
+        Some(end) => end,
+        None => return false,
+    };
+
+    // Entries do not overlap, so the first region that contains the whole
+    // range decides whether the access is permitted.
+    let permitted = |(region_base, region_top, cfg): (u64, u64, usize)| {
+        if start >= region_base && end <= region_top {
+            Some(match access {
+                MemoryAccess::Read => cfg & PMP_R != 0,
+                MemoryAccess::Write => cfg & PMP_W != 0,
+            })
+        } else {
+            None
+        }
+    };
+
+    // The builder may split one region across several adjacent entries, so
+    // contiguous entries with equal permissions are merged before checking.
+    let mut merged: Option<(u64, u64, usize)> = None;
+    let mut previous_top = 0u64;
+    for (i, &addr) in pmp_addr.iter().enumerate() {
+        let entries_per_cfg = core::mem::size_of::<usize>();
+        let cfg = (pmp_cfg[i / entries_per_cfg] >> (8 * (i % entries_per_cfg))) & 0xff;
+        let addr = addr as u64;
+
+        let region = match (cfg >> 3) & 0b11 {
+            // Top of range
+            1 => Some((previous_top, addr << 2)),
+            // Naturally aligned four byte
+            2 => Some((addr << 2, (addr << 2) + 4)),
+            // Naturally aligned power of two, a region which reaches the top of
+            // the address space is clamped to it.
+            3 => {
+                let ones = addr.trailing_ones();
+                let mask = 1u64.checked_shl(ones).map_or(u64::MAX, |bit| bit - 1);
+                let region_base = (addr & !mask) << 2;
+                let region_top = 1u64
+                    .checked_shl(ones + 3)
+                    .and_then(|size| region_base.checked_add(size))
+                    .unwrap_or(u64::MAX);
+                Some((region_base, region_top))
+            }
+            _ => None,
+        };
+
+        previous_top = addr << 2;
+
+        let permissions = cfg & (PMP_R | PMP_W | PMP_X);
+        merged = match (merged, region) {
+            (
+                Some((merged_base, merged_top, merged_permissions)),
+                Some((region_base, region_top)),
+            ) if merged_top == region_base && merged_permissions == permissions => {
+                Some((merged_base, region_top, permissions))
+            }
+            (previous, region) => {
+                if let Some(allowed) = previous.and_then(permitted) {
+                    return allowed;
+                }
+                region.map(|(region_base, region_top)| (region_base, region_top, permissions))
+            }
+        };
+    }
+
+    merged.and_then(permitted).unwrap_or(false)
 }
 
 // # Safety
 // This stores a pointer that aliases task, though this is not used outside of
 // kernel entry/exit.
 #[inline]
-pub unsafe fn set_current_task(task: &task::Task) {
+pub unsafe fn set_current_task(task: &mut task::Task) {
+    task.context_mut().kernel_sp = kernel_stack_top();
     register::mscratch::write(task as *const _ as usize);
 }
 
+// Request that a hart reschedules, by sending it a software interrupt.
+#[cfg(feature = "riscv_aclint")]
+pub fn reschedule_hart(hart: usize) {
+    send_software_interrupt(hart);
+}
+
+// There is only a single hart.
+#[cfg(feature = "riscv_wch_pfic")]
+pub fn reschedule_hart(_hart: usize) {
+    set_software_interrupt();
+}
+
+// Release the other harts once the kernel is initialized, each waits in
+// wait_for_boot_hart for a software interrupt.
+pub fn start_secondary_harts() {
+    for hart in (0..NUM_HARTS).filter(|&hart| hart != BOOT_HART) {
+        reschedule_hart(hart);
+    }
+}
+
 pub fn enter_first_task() -> ! {
+    // Set the highest priority task which may run on this hart as the current
+    // task. No task has run yet, so any task may stand in as the caller while
+    // idle.
+    if !task::with_task_table(task::set_preferred_task) {
+        idle(task::TaskId::new(0).unwrap());
+    }
+
     // Safety: We set mpp to user and then jump to the task entry code in the
     // trap vector. This may fault and return to kernel mode, but is safe as it
     // can not overwrite kernel memory.
@@ -209,22 +507,24 @@ enum RestoreContext {
 
 #[allow(dead_code)]
 mod mcause {
-    pub const INTERRUPT_BIT: usize = 0x80000000;
+    // The interrupt bit is the most significant bit of mcause, whatever the
+    // XLEN.
+    pub const INTERRUPT_BIT: usize = 1 << (usize::BITS - 1);
 
     #[cfg(not(feature = "family_wch_v4c"))]
-    pub const MACHINE_SOFTWARE_INTERRUPT: usize = 0x80000003;
+    pub const MACHINE_SOFTWARE_INTERRUPT: usize = INTERRUPT_BIT | 0x03;
     #[cfg(not(feature = "family_wch_v4c"))]
-    pub const MACHINE_TIMER_INTERRUPT: usize = 0x80000007;
+    pub const MACHINE_TIMER_INTERRUPT: usize = INTERRUPT_BIT | 0x07;
     #[cfg(not(feature = "family_wch_v4c"))]
-    pub const MACHINE_EXTERNAL_INTERRUPT: usize = 0x8000000b;
+    pub const MACHINE_EXTERNAL_INTERRUPT: usize = INTERRUPT_BIT | 0x0b;
 
     // The WCH parts use non standard interrupt numbering.
     #[cfg(feature = "family_wch_v4c")]
-    pub const MACHINE_TIMER_INTERRUPT: usize = 0x8000000c;
+    pub const MACHINE_TIMER_INTERRUPT: usize = INTERRUPT_BIT | 0x0c;
     #[cfg(feature = "family_wch_v4c")]
-    pub const MACHINE_SOFTWARE_INTERRUPT: usize = 0x8000000e;
+    pub const MACHINE_SOFTWARE_INTERRUPT: usize = INTERRUPT_BIT | 0x0e;
     #[cfg(feature = "family_wch_v4c")]
-    pub const EXTERNAL_INTERRUPT_BASE: usize = 0x80000010;
+    pub const EXTERNAL_INTERRUPT_BASE: usize = INTERRUPT_BIT | 0x10;
 
     pub const INSTRUCTION_ADDRESS_MISALIGNED: usize = 0x00000000;
     pub const INSTRUCTION_ACCESS_FAULT: usize = 0x00000001;
@@ -269,12 +569,30 @@ unsafe fn handle_trap(task: *mut task::Task, cause: usize) -> RestoreContext {
     // init, and the reference is immediately discarded.
     let task_idx = unsafe { task::Task::index(&*task) };
 
-    task::with_task_table(|task_table| {
+    // It is possible to schedule a new task in response to any exception,
+    // or in response to a machine software interrupt.
+    let can_switch_task =
+        mcause::is_exception(cause) || (cause == mcause::MACHINE_SOFTWARE_INTERRUPT);
+
+    let restore = task::with_task_table(|task_table| {
+        // Once the full context is saved the task is no longer running. It
+        // may have been stopped by another hart while it was running, in which
+        // case it can not continue.
+        let stopped = if can_switch_task {
+            task_table[task_idx].stop_running()
+        } else {
+            task_table[task_idx].is_stopped()
+        };
+
         let schedule = match cause {
+            // An exception caused by a task which has since been stopped is
+            // discarded.
+            _ if stopped && mcause::is_exception(cause) => task::Schedule::Other,
             // Syscall from a task in user mode.
             mcause::ENVIRONMENT_CALL_FROM_U_MODE => syscall::handle_syscall(task_table, task_idx),
             // Machine software interrupt - used to reschedule tasks in
-            // response to other interrupts.
+            // response to other interrupts, or at the request of another
+            // hart.
             mcause::MACHINE_SOFTWARE_INTERRUPT => {
                 clear_software_interrupt();
 
@@ -292,13 +610,28 @@ unsafe fn handle_trap(task: *mut task::Task, cause: usize) -> RestoreContext {
             #[cfg(feature = "family_wch_v4c")]
             mcause::EXTERNAL_INTERRUPT_BASE.. => handle_interrupt(cause, task_table, task_idx),
             mcause::INTERRUPT_BIT.. => panic!("interrupt"),
-            _ => panic!("exception"),
+            // The first use of the FPU since the task was switched in, its FP
+            // state has been restored and the instruction is retried.
+            #[cfg(feature = "fpu")]
+            mcause::ILLEGAL_INSTRUCTION if fpu::handle_first_use(&task_table[task_idx]) => {
+                task::Schedule::Same
+            }
+            // Any other exception was caused by the task, so only that task
+            // should enter the fatal state.
+            _ => {
+                trace::record(trace::Event::Fault, task_idx.into(), cause as u32);
+                let record = task_table[task_idx].context().fault_record(cause);
+                task::do_fault(task_table, task_idx, record)
+            }
         };
 
-        // It is possible to schedule a new task in response to any exception,
-        // or in response to a machine software interrupt.
-        let can_switch_task =
-            mcause::is_exception(cause) || (cause == mcause::MACHINE_SOFTWARE_INTERRUPT);
+        let schedule = if stopped {
+            task::Schedule::Other
+        } else {
+            schedule
+        };
+
+        trace::record(trace::Event::Schedule, task_idx.into(), schedule.into());
 
         match (schedule, can_switch_task) {
             // We can always schedule the same task.
@@ -308,41 +641,134 @@ unsafe fn handle_trap(task: *mut task::Task, cause: usize) -> RestoreContext {
                 // Ecall always requires a full restore as syscalls may return
                 // data in any register.
                 if cause == mcause::ENVIRONMENT_CALL_FROM_U_MODE {
-                    RestoreContext::Full
+                    Some(RestoreContext::Full)
                 } else {
-                    RestoreContext::Partial
+                    Some(RestoreContext::Partial)
                 }
             }
 
             // We know exactly which new task to schedule.
-            (task::Schedule::Exactly(new_task_idx), true) => {
+            (task::Schedule::Exactly(new_task_idx), true)
+                if task_table[new_task_idx].can_run_on(hart_id()) =>
+            {
                 assert!(new_task_idx != task_idx.into());
+
+                #[cfg(feature = "fpu")]
+                fpu::switch_out(&mut task_table[task_idx]);
+
                 task_table[new_task_idx].set_as_current();
 
-                RestoreContext::Full
+                Some(RestoreContext::Full)
             }
 
-            // We don't know which new task to schedule and need to do a
-            // priority scan.
-            (task::Schedule::Other, true) => {
-                let new_task = task::get_preferred_task(task_table);
-                new_task.set_as_current();
+            // We don't know which new task to schedule, or the task is pinned
+            // to another hart, and need to do a priority scan. If no task is
+            // ready the hart must idle.
+            (_, true) => {
+                #[cfg(feature = "fpu")]
+                fpu::switch_out(&mut task_table[task_idx]);
 
-                RestoreContext::Full
+                if task::set_preferred_task(task_table) {
+                    Some(RestoreContext::Full)
+                } else {
+                    None
+                }
             }
 
             // We need to schedule some other task and currently can't - so we
             // need to trigger a software interrupt.
             (_, false) => {
-                // Schedule the same task but request a software interrupt
-                // to reschedule on.
-                task_table[task_idx].set_as_current();
+                // Resume the same task but request a software interrupt to
+                // reschedule on. If the task was stopped, or faulted by its
+                // watchdog, the software interrupt is taken before it can
+                // execute any further.
+                // Safety: This aliases the task however the aliased pointer
+                // is not used outside of kernel entry/exit.
+                unsafe { set_current_task(&mut task_table[task_idx]) };
                 set_software_interrupt();
 
-                RestoreContext::Partial
+                Some(RestoreContext::Partial)
             }
         }
-    })
+    });
+
+    match restore {
+        Some(restore) => restore,
+        None => {
+            idle(task_idx);
+            RestoreContext::Full
+        }
+    }
+}
+
+// Called when no task is ready to run on this hart. Sleep until an interrupt
+// is pending and then handle it, until a task becomes ready and is set as the
+// current task. The task table is unlocked while sleeping so that other harts
+// may continue. Interrupts are disabled while in the kernel, but a pending
+// interrupt still wakes the hart.
+fn idle(caller_idx: task::TaskId) {
+    loop {
+        let next_deadline = task::with_task_table(|task_table| {
+            handle_pending_interrupts(task_table, caller_idx);
+
+            // Any schedule resulting from the interrupts is irrelevant, the
+            // ready queue is checked again once they have been handled.
+            if task::set_preferred_task(task_table) {
+                return None;
+            }
+
+            trace::record(trace::Event::Idle, caller_idx.into(), 0);
+            Some(time::next_deadline())
+        });
+
+        match next_deadline {
+            Some(next_deadline) => sleep(next_deadline),
+            None => return,
+        }
+    }
+}
+
+#[cfg(not(feature = "family_wch_v4c"))]
+fn sleep(_next_deadline: Option<u64>) {
+    // Safety: Waiting for an interrupt has no effect on memory.
+    unsafe { core::arch::asm!("wfi", options(nomem, nostack)) };
+}
+
+// Wait for the boot hart to release this hart with a software interrupt, see
+// start_secondary_harts. Interrupts are disabled, so the software interrupt
+// wakes the hart without taking a trap.
+#[cfg(not(feature = "family_wch_v4c"))]
+pub fn wait_for_boot_hart() {
+    // Safety: Enabling the software interrupt has no effect until interrupts
+    // are enabled.
+    unsafe { register::mie::set_msoft() };
+
+    while !register::mip::read().msoft() {
+        sleep(None);
+    }
+
+    clear_software_interrupt();
+}
+
+// Handle any pending interrupts without taking a trap.
+#[cfg(not(feature = "family_wch_v4c"))]
+fn handle_pending_interrupts(task_table: &mut task::TaskTable, caller_idx: task::TaskId) {
+    let mip = register::mip::read();
+
+    // There is no task to switch away from, so there is nothing to do for a
+    // software interrupt other than clear it.
+    if mip.msoft() {
+        clear_software_interrupt();
+    }
+
+    if mip.mtimer() {
+        clear_timer_interrupt();
+        time::handle_timer_expiration(task_table, caller_idx);
+    }
+
+    if mip.mext() {
+        handle_interrupt(mcause::MACHINE_EXTERNAL_INTERRUPT, task_table, caller_idx);
+    }
 }
 
 #[repr(align(4))]
@@ -352,6 +778,7 @@ unsafe extern "C" fn trap_vector() -> ! {
     // relaxation and so these are reserved for platform use.
 
     extern "C" {
+        static _stack_start: c_void;
         static _stack_end: c_void;
     }
 
@@ -374,41 +801,41 @@ unsafe extern "C" fn trap_vector() -> ! {
         "beq a0, zero, 3f", // kernel_exception
 
         // Save stack pointer, this is callee saved but we necessarily trash it
-        "sw sp, {task_sp}(a0)",
+        sx!("sp, {task_sp}(a0)"),
 
         // Save caller saved registers
-        "sw ra, {task_ra}(a0)",
+        sx!("ra, {task_ra}(a0)"),
 
-        "sw a1, {task_a1}(a0)",
-        "sw a2, {task_a2}(a0)",
-        "sw a3, {task_a3}(a0)",
-        "sw a4, {task_a4}(a0)",
-        "sw a5, {task_a5}(a0)",
-        "sw a6, {task_a6}(a0)",
-        "sw a7, {task_a7}(a0)",
+        sx!("a1, {task_a1}(a0)"),
+        sx!("a2, {task_a2}(a0)"),
+        sx!("a3, {task_a3}(a0)"),
+        sx!("a4, {task_a4}(a0)"),
+        sx!("a5, {task_a5}(a0)"),
+        sx!("a6, {task_a6}(a0)"),
+        sx!("a7, {task_a7}(a0)"),
 
         // a0 was saved to mscratch upon entry, save a0 and clear mscratch
         "csrrw a1, mscratch, zero",
-        "sw a1, {task_a0}(a0)",
+        sx!("a1, {task_a0}(a0)"),
 
-        "sw t0, {task_t0}(a0)",
-        "sw t1, {task_t1}(a0)",
-        "sw t2, {task_t2}(a0)",
-        "sw t3, {task_t3}(a0)",
-        "sw t4, {task_t4}(a0)",
-        "sw t5, {task_t5}(a0)",
-        "sw t6, {task_t6}(a0)",
+        sx!("t0, {task_t0}(a0)"),
+        sx!("t1, {task_t1}(a0)"),
+        sx!("t2, {task_t2}(a0)"),
+        sx!("t3, {task_t3}(a0)"),
+        sx!("t4, {task_t4}(a0)"),
+        sx!("t5, {task_t5}(a0)"),
+        sx!("t6, {task_t6}(a0)"),
 
-        // Switch to the kernel stack
-        "la sp, {kernel_top_of_stack}",
+        // Switch to this hart's kernel stack
+        lx!("sp, {task_kernel_sp}(a0)"),
 
         // Check if we need to save the full context.
         "csrr a1, mcause",
 
         // First, check if the interrupt bit is zero, if so we need to perform
-        // a full save as this is an exception.
-        "srli a2, a1, 31",
-        "beqz a2, 2f", // full_save:
+        // a full save as this is an exception. The interrupt bit is the sign
+        // bit.
+        "bgez a1, 2f", // full_save:
 
         // Second, check if it's a machine software interrupt, if it is then we
         // also need to save the full context.
@@ -426,53 +853,53 @@ unsafe extern "C" fn trap_vector() -> ! {
 
         "1:", // partial_restore:
         // Switch back to user stack
-        "lw sp, {task_sp}(a2)",
+        lx!("sp, {task_sp}(a2)"),
 
         // Restore caller saved registers
-        "lw ra, {task_ra}(a2)",
-
-        "lw t0, {task_t0}(a2)",
-        "lw t1, {task_t1}(a2)",
-        "lw t2, {task_t2}(a2)",
-        "lw t3, {task_t3}(a2)",
-        "lw t4, {task_t4}(a2)",
-        "lw t5, {task_t5}(a2)",
-        "lw t6, {task_t6}(a2)",
-
-        "lw a0, {task_a0}(a2)",
-        "lw a1, {task_a1}(a2)",
+        lx!("ra, {task_ra}(a2)"),
+
+        lx!("t0, {task_t0}(a2)"),
+        lx!("t1, {task_t1}(a2)"),
+        lx!("t2, {task_t2}(a2)"),
+        lx!("t3, {task_t3}(a2)"),
+        lx!("t4, {task_t4}(a2)"),
+        lx!("t5, {task_t5}(a2)"),
+        lx!("t6, {task_t6}(a2)"),
+
+        lx!("a0, {task_a0}(a2)"),
+        lx!("a1, {task_a1}(a2)"),
         // Restore a2 last as it is used for the task pointer
-        "lw a3, {task_a3}(a2)",
-        "lw a4, {task_a4}(a2)",
-        "lw a5, {task_a5}(a2)",
-        "lw a6, {task_a6}(a2)",
-        "lw a7, {task_a7}(a2)",
+        lx!("a3, {task_a3}(a2)"),
+        lx!("a4, {task_a4}(a2)"),
+        lx!("a5, {task_a5}(a2)"),
+        lx!("a6, {task_a6}(a2)"),
+        lx!("a7, {task_a7}(a2)"),
 
-        "lw a2, {task_a2}(a2)",
+        lx!("a2, {task_a2}(a2)"),
 
         // Return from interrupt
         "mret",
 
         "2:", // full_save:
         // Save the remaining registers
-        "sw gp, {task_gp}(a0)",
-        "sw tp, {task_tp}(a0)",
-
-        "sw s0, {task_s0}(a0)",
-        "sw s1, {task_s1}(a0)",
-        "sw s2, {task_s2}(a0)",
-        "sw s3, {task_s3}(a0)",
-        "sw s4, {task_s4}(a0)",
-        "sw s5, {task_s5}(a0)",
-        "sw s6, {task_s6}(a0)",
-        "sw s7, {task_s7}(a0)",
-        "sw s8, {task_s8}(a0)",
-        "sw s9, {task_s9}(a0)",
-        "sw s10, {task_s10}(a0)",
-        "sw s11, {task_s11}(a0)",
+        sx!("gp, {task_gp}(a0)"),
+        sx!("tp, {task_tp}(a0)"),
+
+        sx!("s0, {task_s0}(a0)"),
+        sx!("s1, {task_s1}(a0)"),
+        sx!("s2, {task_s2}(a0)"),
+        sx!("s3, {task_s3}(a0)"),
+        sx!("s4, {task_s4}(a0)"),
+        sx!("s5, {task_s5}(a0)"),
+        sx!("s6, {task_s6}(a0)"),
+        sx!("s7, {task_s7}(a0)"),
+        sx!("s8, {task_s8}(a0)"),
+        sx!("s9, {task_s9}(a0)"),
+        sx!("s10, {task_s10}(a0)"),
+        sx!("s11, {task_s11}(a0)"),
 
         "csrr a2, mepc",
-        "sw a2, {task_pc}(a0)",
+        sx!("a2, {task_pc}(a0)"),
 
         // handle_trap(task: a0, mcause: a1) -> (full_switch: a0)
         "jal {handle_trap}",
@@ -483,27 +910,27 @@ unsafe extern "C" fn trap_vector() -> ! {
 
         // Always restore mepc here, we need to advance pc on an ecall even if
         // we didn't switch task.
-        "lw a3, {task_pc}(a2)",
+        lx!("a3, {task_pc}(a2)"),
         "csrw mepc, a3",
 
         // Check if we can avoid the full context restore
         "beq a0, zero, 1b", // partial_restore:
 
         // Restore callee saved registers
-        "lw gp, {task_gp}(a2)",
-        "lw tp, {task_tp}(a2)",
-        "lw s0, {task_s0}(a2)",
-        "lw s1, {task_s1}(a2)",
-        "lw s2, {task_s2}(a2)",
-        "lw s3, {task_s3}(a2)",
-        "lw s4, {task_s4}(a2)",
-        "lw s5, {task_s5}(a2)",
-        "lw s6, {task_s6}(a2)",
-        "lw s7, {task_s7}(a2)",
-        "lw s8, {task_s8}(a2)",
-        "lw s9, {task_s9}(a2)",
-        "lw s10, {task_s10}(a2)",
-        "lw s11, {task_s11}(a2)",
+        lx!("gp, {task_gp}(a2)"),
+        lx!("tp, {task_tp}(a2)"),
+        lx!("s0, {task_s0}(a2)"),
+        lx!("s1, {task_s1}(a2)"),
+        lx!("s2, {task_s2}(a2)"),
+        lx!("s3, {task_s3}(a2)"),
+        lx!("s4, {task_s4}(a2)"),
+        lx!("s5, {task_s5}(a2)"),
+        lx!("s6, {task_s6}(a2)"),
+        lx!("s7, {task_s7}(a2)"),
+        lx!("s8, {task_s8}(a2)"),
+        lx!("s9, {task_s9}(a2)"),
+        lx!("s10, {task_s10}(a2)"),
+        lx!("s11, {task_s11}(a2)"),
 
         // Continue with rest of context restore
         "j 1b", // partial_restore:
@@ -512,7 +939,7 @@ unsafe extern "C" fn trap_vector() -> ! {
         // there is no task. Set the stack so we can at least try to leave some
         // breadcrumbs, pass mcause to match handle_trap.
         "3:", // kernel_exception:
-        "la sp, {kernel_top_of_stack}",
+        hart_stack_asm!(),
         "csrr a0, mcause",
         "j {handle_kernel_exception}",
 
@@ -556,10 +983,14 @@ unsafe extern "C" fn trap_vector() -> ! {
         task_s10 = const register_offset!(s10),
         task_s11 = const register_offset!(s11),
 
+        task_kernel_sp = const register_offset!(kernel_sp),
+
         handle_trap = sym handle_trap,
         handle_kernel_exception = sym handle_kernel_exception,
 
+        kernel_stack_start = sym _stack_start,
         kernel_top_of_stack = sym _stack_end,
+        num_harts = const NUM_HARTS,
         options(noreturn));
     }
 }
@@ -637,11 +1068,16 @@ unsafe extern "C" fn _start() -> ! {
         static _bss_start: c_void;
         static _bss_end: c_void;
 
+        static _stack_start: c_void;
         static _stack_end: c_void;
     }
 
     unsafe {
         core::arch::asm!(
+        // Every hart starts here, only the boot hart initializes memory.
+        "csrr t0, mhartid",
+        "bnez t0, 5f", // secondary_hart:
+
         // Load .data from flash
         "la a0, {_data_start}",
         "la a1, {_data_end}",
@@ -666,23 +1102,40 @@ unsafe extern "C" fn _start() -> ! {
         "4:",
 
         // Switch to the kernel stack
-        "la sp, {_stack_end}",
+        hart_stack_asm!(),
 
         // Let's go!
         "j {kernel_init}",
 
+        // Any hart beyond those that the kernel is configured for is parked.
+        "5:", // secondary_hart:
+        "li t1, {num_harts}",
+        "bgeu t0, t1, 6f", // park:
+
+        // Switch to this hart's kernel stack, and wait for the boot hart.
+        hart_stack_asm!(),
+        "j {kernel_secondary_init}",
+
+        "6:", // park:
+        "wfi",
+        "j 6b", // park:
+
         _data_start   = sym _data_start,
         _data_end     = sym _data_end,
         _data_load    = sym _data_load,
         _bss_start    = sym _bss_start,
         _bss_end      = sym _bss_end,
-        _stack_end    = sym _stack_end,
+        kernel_stack_start = sym _stack_start,
+        kernel_top_of_stack = sym _stack_end,
+        num_harts     = const NUM_HARTS,
         kernel_init   = sym init::kernel_init,
+        kernel_secondary_init = sym init::kernel_secondary_init,
         options(noreturn));
     }
 }
 
-pub fn arch_init() {
+// Architectural init performed by every hart.
+fn hart_init() {
     // Ensure that mscratch is zero before setting up interrupts, this ensures
     // that if we take a fault before the next task is set we don't write to
     // random memory attempting to save the context.
@@ -701,8 +1154,22 @@ pub fn arch_init() {
     #[cfg(not(feature = "family_wch_v4c"))]
     {
         unsafe {
-            register::mie::set_mtimer();
             register::mie::set_msoft();
+        }
+    }
+
+    #[cfg(feature = "fpu")]
+    fpu::fpu_hart_init();
+}
+
+pub fn arch_init() {
+    hart_init();
+
+    // Only the boot hart takes timer and external interrupts.
+    #[cfg(not(feature = "family_wch_v4c"))]
+    {
+        unsafe {
+            register::mie::set_mtimer();
             register::mie::set_mext();
         }
     }
@@ -722,3 +1189,12 @@ pub fn arch_init() {
     #[cfg(feature = "riscv_wch_systick")]
     wch_systick::wch_systick_init();
 }
+
+// Architectural init for a hart other than the boot hart, which only takes
+// software interrupts.
+#[cfg(feature = "riscv_aclint")]
+pub fn secondary_hart_init() {
+    hart_init();
+
+    aclint::aclint_hart_init();
+}
@@ -6,10 +6,16 @@ use crate::arch::riscv::*;
 #[rtos_import]
 pub static mut PERIPHERAL_PLIC_BASE: u32;
 
+// External interrupts are only enabled for context 0, the machine mode context
+// of the boot hart, so they are only claimed by the boot hart. A task completes
+// its interrupts from whichever hart it runs on, so completion is always for
+// context 0 rather than the current hart's context.
+const CONTEXT: usize = 0;
+
 pub fn plic_init() {
     // Set the threshold to zero - enables all interrupts.
     let plic = unsafe { Plic::from_ptr(&mut PERIPHERAL_PLIC_BASE as *mut _ as *mut _) };
-    plic.threshold(0).write_value(0);
+    plic.threshold(CONTEXT).write_value(0);
 }
 
 pub fn reset_interrupt(interrupt: usize) {
@@ -21,12 +27,12 @@ pub fn reset_interrupt(interrupt: usize) {
 
 fn claim_interrupt() -> u32 {
     let plic = unsafe { Plic::from_ptr(&mut PERIPHERAL_PLIC_BASE as *mut _ as *mut _) };
-    plic.claim(0).read()
+    plic.claim(CONTEXT).read()
 }
 
 fn complete_interrupt(interrupt: usize) {
     let plic = unsafe { Plic::from_ptr(&mut PERIPHERAL_PLIC_BASE as *mut _ as *mut _) };
-    plic.complete(0).write_value(interrupt as u32);
+    plic.complete(CONTEXT).write_value(interrupt as u32);
 }
 
 fn disable_interrupt(interrupt: usize) {
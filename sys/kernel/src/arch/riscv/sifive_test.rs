@@ -0,0 +1,17 @@
+use rtos_macros::rtos_import;
+
+#[rtos_import]
+pub static mut PERIPHERAL_SIFIVE_TEST_BASE: u32;
+
+const FINISHER_RESET: u32 = 0x7777;
+
+pub fn system_reset() -> ! {
+    // Safety: Writes to the test device register, the system is reset so no
+    // kernel state is observed after this point.
+    unsafe {
+        core::ptr::write_volatile(&mut PERIPHERAL_SIFIVE_TEST_BASE as *mut u32, FINISHER_RESET);
+    }
+
+    // The reset is not immediate, wait for it to take effect.
+    loop {}
+}
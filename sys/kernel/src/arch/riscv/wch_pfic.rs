@@ -1,4 +1,4 @@
-use pac_qingke::pfic::{Interrupt, Pfic};
+use pac_qingke::pfic::{Interrupt, Keycode, Pfic};
 use rtos_macros::rtos_import;
 
 use crate::arch::riscv::*;
@@ -6,6 +6,9 @@ use crate::arch::riscv::*;
 #[rtos_import]
 pub static mut PERIPHERAL_PFIC_BASE: usize;
 
+#[rtos_import]
+static DEEP_SLEEP: bool;
+
 pub fn wch_pfic_init() {
     // Enable the default set of interrupts. WCH parts do not use the MIE CSR.
     // Safety: This writes to a PFIC register, but does not affect current
@@ -90,6 +93,57 @@ pub fn handle_interrupt(
     task::handle_interrupt(task_table, caller_idx, interrupt)
 }
 
+// Handle every enabled interrupt that is pending, without taking a trap.
+pub fn handle_pending_interrupts(task_table: &mut task::TaskTable, caller_idx: task::TaskId) {
+    const EXTERNAL_INTERRUPT_MIN: usize =
+        super::mcause::EXTERNAL_INTERRUPT_BASE & !super::mcause::INTERRUPT_BIT;
+
+    // Safety: Reads PFIC status registers.
+    let pfic = unsafe { Pfic::from_ptr(&mut PERIPHERAL_PFIC_BASE as *mut _ as *mut _) };
+
+    for word in 0..8 {
+        let mut pending = pfic.ipr(word).read().0 & pfic.isr(word).read().0;
+
+        while pending != 0 {
+            let interrupt = word * 32 + pending.trailing_zeros() as usize;
+            pending &= pending - 1;
+
+            match interrupt {
+                x if x == u8::from(Interrupt::SYSTICK) as usize => {
+                    clear_timer_interrupt();
+                    time::handle_timer_expiration(task_table, caller_idx);
+                }
+                // There is no task to switch away from, so there is nothing
+                // to do for a software interrupt other than clear it.
+                x if x == u8::from(Interrupt::SWI) as usize => clear_software_interrupt(),
+                EXTERNAL_INTERRUPT_MIN.. => {
+                    handle_interrupt(
+                        super::mcause::INTERRUPT_BIT | interrupt,
+                        task_table,
+                        caller_idx,
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Sleep until an interrupt is pending. Deep sleep stops the system clock, and
+// with it SysTick, so it is only entered when the app enables it and there is
+// no deadline that SysTick would need to wake us for.
+pub fn sleep(next_deadline: Option<u64>) {
+    // Safety: Reads an immutable constant.
+    let deep = unsafe { DEEP_SLEEP } && next_deadline.is_none();
+
+    // Safety: Writes to a PFIC register, this only affects the next WFI.
+    let pfic = unsafe { Pfic::from_ptr(&mut PERIPHERAL_PFIC_BASE as *mut _ as *mut _) };
+    pfic.sctlr().modify(|x| x.set_sleepdeep(deep));
+
+    // Safety: Waiting for an interrupt has no effect on memory.
+    unsafe { core::arch::asm!("wfi", options(nomem, nostack)) };
+}
+
 pub fn interrupt_control(interrupt: usize, control: task::InterruptControl) {
     match control {
         task::InterruptControl::Disable => disable_interrupt(interrupt),
@@ -117,3 +171,16 @@ pub fn clear_timer_interrupt() {
 
     clear_pending_interrupt(u8::from(Interrupt::SYSTICK) as usize);
 }
+
+pub fn system_reset() -> ! {
+    // Safety: Writes to a PFIC register, the system is reset so no kernel
+    // state is observed after this point.
+    let pfic = unsafe { Pfic::from_ptr(&mut PERIPHERAL_PFIC_BASE as *mut _ as *mut _) };
+    pfic.cfgr().write(|x| {
+        x.set_keycode(Keycode::KEY3);
+        x.set_sysreset(true);
+    });
+
+    // The reset is not immediate, wait for it to take effect.
+    loop {}
+}
@@ -0,0 +1,98 @@
+use core::{fmt::Write, mem::MaybeUninit};
+
+use zerocopy::FromZeroes;
+
+use crate::{arch, task};
+
+// The record is placed in .noinit, which is not zeroed at boot, so that it
+// survives a reset. It is only valid if magic is CRASH_RECORD_MAGIC.
+#[link_section = ".noinit"]
+static mut CRASH_RECORD: MaybeUninit<arch::CrashRecord> = MaybeUninit::uninit();
+
+// Whether a task fault has been recorded since boot.
+static mut TASK_FAULT_RECORDED: bool = false;
+
+// # Safety
+// - Nothing else may be accessing CRASH_RECORD.
+unsafe fn crash_record() -> &'static mut arch::CrashRecord {
+    // Safety: Every bit pattern is a valid CrashRecord, so the record is
+    // initialized even though it is never written at boot. The caller ensures
+    // that this is the only reference.
+    unsafe { CRASH_RECORD.assume_init_mut() }
+}
+
+// Writes a message into a fixed size buffer, truncating it at a character
+// boundary.
+struct MessageWriter<'a> {
+    buffer: &'a mut [u8; arch::CRASH_MESSAGE_LENGTH],
+    len: usize,
+}
+
+impl Write for MessageWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut n = s.len().min(self.buffer.len() - self.len);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+
+        self.buffer[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+pub fn record_task_fault(task: &task::Task, fault: arch::FaultRecord) {
+    // Safety: We hold a reference to a task, so TASK_TABLE_LOCK must be held
+    // and nothing else may be accessing CRASH_RECORD.
+    let record = unsafe { crash_record() };
+
+    record.kind = arch::CrashKind::TaskFault;
+    record.task = u8::from(task.index()) as u32;
+    record.fault = fault;
+    record.registers = task.context().registers();
+    record.message = [0; arch::CRASH_MESSAGE_LENGTH];
+    record.magic = arch::CRASH_RECORD_MAGIC;
+
+    // Safety: As above.
+    unsafe { TASK_FAULT_RECORDED = true };
+}
+
+pub fn record_panic(info: &core::panic::PanicInfo) {
+    // Safety: The kernel is about to stop, nothing else runs once it has
+    // panicked.
+    let record = unsafe { crash_record() };
+
+    // Keep a task fault recorded since boot, it may have caused the panic.
+    // Safety: As above.
+    if !unsafe { TASK_FAULT_RECORDED } {
+        record.task = u32::MAX;
+        record.fault = arch::FaultRecord::new_zeroed();
+        record.registers = [0; 32];
+    }
+
+    record.kind = arch::CrashKind::KernelPanic;
+    record.message = [0; arch::CRASH_MESSAGE_LENGTH];
+    let _ = write!(
+        MessageWriter {
+            buffer: &mut record.message,
+            len: 0,
+        },
+        "{}",
+        info
+    );
+    record.magic = arch::CRASH_RECORD_MAGIC;
+}
+
+// Remove and return the crash record, if there is a valid one.
+pub fn take_record() -> Option<arch::CrashRecord> {
+    // Safety: Records are only taken through a syscall, so TASK_TABLE_LOCK
+    // must be held and nothing else may be accessing CRASH_RECORD.
+    let record = unsafe { crash_record() };
+
+    if record.magic == arch::CRASH_RECORD_MAGIC {
+        record.magic = 0;
+        Some(*record)
+    } else {
+        None
+    }
+}
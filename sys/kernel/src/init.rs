@@ -1,8 +1,9 @@
-use crate::{app, arch, task};
+use crate::{app, arch, crash, task};
 
 #[panic_handler]
 #[cfg(target_os = "none")]
 fn panic(_info: &core::panic::PanicInfo) -> ! {
+    crash::record_panic(_info);
     semihosting::println!("panic {}", _info);
     loop {}
 }
@@ -19,5 +20,20 @@ pub unsafe fn kernel_init() -> ! {
     // Load initial state from the task initialization table.
     task::task_init();
 
+    arch::start_secondary_harts();
+
+    arch::enter_first_task()
+}
+
+// # Safety
+// - This should only be called from the architecture specific entry point, on
+//   a hart other than the boot hart.
+#[cfg(not(feature = "family_wch_v4c"))]
+pub unsafe fn kernel_secondary_init() -> ! {
+    // The kernel and task table are initialized by the boot hart.
+    arch::wait_for_boot_hart();
+
+    arch::secondary_hart_init();
+
     arch::enter_first_task()
 }
@@ -7,7 +7,9 @@
 
 mod app;
 mod arch;
+mod crash;
 mod init;
 mod syscall;
 mod task;
 mod time;
+mod trace;
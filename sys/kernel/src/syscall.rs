@@ -1,7 +1,12 @@
+use core::{
+    mem::{align_of, size_of},
+    num::NonZeroU32,
+};
+
 pub use kernel_types::syscall::*;
 use zerocopy::{AsBytes, FromBytes, FromZeroes, Ref};
 
-use crate::{arch, task};
+use crate::{arch, task, trace};
 
 #[repr(C)]
 #[derive(AsBytes, FromBytes, FromZeroes)]
@@ -49,28 +54,34 @@ fn do_sys_panic(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> t
 #[derive(AsBytes, FromBytes, FromZeroes)]
 struct SysReceiveInput {
     out_capacity: u8,
-    _pad: [u8; 3],
+    _pad: [u8; size_of::<usize>() - 1],
 }
 
 #[repr(C)]
 #[derive(AsBytes, FromBytes, FromZeroes)]
 struct SysReceiveOutput {
     sender: u8,
-    _pad0: u8,
+    leases: u8,
     len: u8,
-    _pad1: u8,
+    missed_periods: u8,
+    _pad0: [u8; size_of::<usize>() - 4],
     notifications: u32,
+    _pad1: [u8; size_of::<usize>() - 4],
     data: [usize; abi::MAX_MESSAGE_SIZE],
 }
 
-// Wait for a message or notification.
-// fn SYS_RECEIVE() -> (sender: u8, len: u8, notifications: u32, data: [u8; len])
+// Wait for a message or notification. missed_periods only counts the missed
+// expirations of timer slot 0, those of other slots are read with
+// SYS_TIMER_MISSED.
+// fn SYS_RECEIVE() -> (sender: u8, leases: u8, len: u8, missed_periods: u8, notifications: u32,
+//     data: [u8; len])
 fn do_sys_receive(task_table: &mut task::TaskTable, caller: task::TaskId) -> task::Schedule {
     task::do_receive(task_table, caller)
 }
 
 pub fn set_receive_result(target: &mut task::Task, caller: Option<&task::Task>) {
     let notifications = target.notifications();
+    let missed_periods = target.take_timer_missed(0);
     target.reset_notifications();
 
     let target_input = target.context().sys_registers().input::<SysReceiveInput>();
@@ -82,6 +93,7 @@ pub fn set_receive_result(target: &mut task::Task, caller: Option<&task::Task>)
         .output::<SysReceiveOutput>();
 
     target_output.notifications = notifications;
+    target_output.missed_periods = missed_periods;
 
     if let Some(sender) = caller {
         let caller_input = sender.context().sys_registers().input::<SysCallInput>();
@@ -90,10 +102,12 @@ pub fn set_receive_result(target: &mut task::Task, caller: Option<&task::Task>)
         let len = out_capacity.min(in_len) as usize;
 
         target_output.sender = sender.index().into();
+        target_output.leases = caller_input.num_leases;
         target_output.len = len as u8;
         target_output.data[..len].copy_from_slice(&caller_input.data[..len]);
     } else {
         target_output.sender = u8::MAX;
+        target_output.leases = 0;
         target_output.len = 0;
     }
 }
@@ -104,93 +118,195 @@ struct SysSendInput {
     target: u8,
     _pad0: u8,
     in_len: u8,
-    _pad1: [u8; 5],
+    _pad1: [u8; 2 * size_of::<usize>() - 3],
     data: [usize; abi::MAX_MESSAGE_SIZE],
 }
 
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysSendOutput {
+    status: abi::IpcStatus,
+    _pad: [u8; size_of::<usize>() - 1],
+}
+
 // Send a response to a task, must be a task that sent a message with SYS_CALL.
-// fn SYS_SEND(target: u8, len: u8, data: [u8; len])
+// fn SYS_SEND(target: u8, len: u8, data: [u8; len]) -> (status: u8)
 fn do_sys_send(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
     let caller = &mut task_table[caller_idx];
     let input = caller.context().sys_registers().input::<SysSendInput>();
 
-    if let Some(target_idx) = task::TaskId::new(input.target) {
-        // The message length must be in bounds abi::MAX_MESSAGE_SIZE.
-        if input.in_len as usize > abi::MAX_MESSAGE_SIZE {
-            return task::do_panic(task_table, caller_idx);
-        }
+    // The message length must be in bounds abi::MAX_MESSAGE_SIZE.
+    if input.in_len as usize > abi::MAX_MESSAGE_SIZE {
+        return task::do_panic(task_table, caller_idx);
+    }
 
+    if let Some(target_idx) = task::TaskId::new(input.target) {
         task::do_send(task_table, caller_idx, target_idx)
     } else {
-        task::do_panic(task_table, caller_idx)
+        set_send_result(caller, abi::IpcStatus::InvalidTarget);
+        task::Schedule::Same
     }
 }
 
+pub fn set_send_result(caller: &mut task::Task, status: abi::IpcStatus) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysSendOutput>();
+
+    output.status = status;
+}
+
 #[repr(C)]
 #[derive(AsBytes, FromBytes, FromZeroes)]
 struct SysCallInput {
     target: u8,
-    _pad0: u8,
+    num_leases: u8,
     in_len: u8,
     out_capacity: u8,
-    _pad1: u32,
+    _pad0: [u8; size_of::<usize>() - 4],
+    timeout: u32,
+    _pad1: [u8; size_of::<usize>() - 4],
     data: [usize; abi::MAX_MESSAGE_SIZE],
+    // The lease table is passed in s5, beyond any register that may be used
+    // for message data.
+    _pad2: [usize; 6],
+    leases: usize,
 }
 
 #[repr(C)]
 #[derive(AsBytes, FromBytes, FromZeroes)]
 struct SysCallOutput {
     len: u8,
-    _pad: [u8; 7],
+    status: abi::IpcStatus,
+    _pad: [u8; 2 * size_of::<usize>() - 2],
     data: [usize; abi::MAX_MESSAGE_SIZE],
 }
 
-// Make a remote call to another task.
-// fn SYS_CALL(target: u8, len: u8, data: [u8; len]) -> (len: u8, data [u8; len])
+// Make a remote call to another task, optionally lending regions of memory to
+// the target until it responds.
+// fn SYS_CALL(target: u8, num_leases: u8, len: u8, data: [u8; len], leases: *const Lease)
+//     -> (len: u8, status: u8, data [u8; len])
 fn do_sys_call(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
+    call(task_table, caller_idx, None)
+}
+
+// Make a remote call to another task, abandoning the call if no response is
+// received within timeout microseconds. A zero timeout never expires.
+// fn SYS_CALL_TIMEOUT(target: u8, num_leases: u8, len: u8, timeout: u32, data: [u8; len],
+//     leases: *const Lease) -> (len: u8, status: u8, data [u8; len])
+fn do_sys_call_timeout(
+    task_table: &mut task::TaskTable,
+    caller_idx: task::TaskId,
+) -> task::Schedule {
+    let input = task_table[caller_idx]
+        .context()
+        .sys_registers()
+        .input::<SysCallInput>();
+    let timeout = NonZeroU32::new(input.timeout);
+    call(task_table, caller_idx, timeout)
+}
+
+// The shared implementation of SYS_CALL and SYS_CALL_TIMEOUT.
+fn call(
+    task_table: &mut task::TaskTable,
+    caller_idx: task::TaskId,
+    timeout: Option<NonZeroU32>,
+) -> task::Schedule {
     let caller = &mut task_table[caller_idx];
     let input = caller.context().sys_registers().input::<SysCallInput>();
 
-    if let Some(target_idx) = task::TaskId::new(input.target) {
-        // The message length must be in bounds abi::MAX_MESSAGE_SIZE.
-        if input.in_len as usize > abi::MAX_MESSAGE_SIZE {
-            return task::do_panic(task_table, caller_idx);
-        }
+    // The message length must be in bounds abi::MAX_MESSAGE_SIZE.
+    if input.in_len as usize > abi::MAX_MESSAGE_SIZE {
+        return task::do_panic(task_table, caller_idx);
+    }
 
-        task::do_call(task_table, caller_idx, target_idx)
+    // The lease table must be readable by the caller, the leases themselves
+    // are only checked when they are used.
+    if !leases_valid(caller, input) {
+        return task::do_panic(task_table, caller_idx);
+    }
+
+    if let Some(target_idx) = task::TaskId::new(input.target) {
+        task::do_call(task_table, caller_idx, target_idx, timeout)
     } else {
-        task::do_panic(task_table, caller_idx)
+        set_call_result(caller, None, abi::IpcStatus::InvalidTarget);
+        task::Schedule::Same
     }
 }
 
-pub fn set_call_result(target: &mut task::Task, caller: &task::Task) {
-    let target_input = target.context().sys_registers().input::<SysCallInput>();
-    let caller_input = caller.context().sys_registers().input::<SysSendInput>();
+fn leases_valid(caller: &task::Task, input: &SysCallInput) -> bool {
+    if input.num_leases == 0 {
+        return true;
+    }
 
-    let out_capacity = target_input.out_capacity;
-    let in_len = caller_input.in_len;
+    let len = input.num_leases as usize * size_of::<abi::Lease>();
+    input.leases % align_of::<abi::Lease>() == 0
+        && arch::can_access(caller, input.leases, len, arch::MemoryAccess::Read)
+}
+
+// Get a lease attached to the SYS_CALL that the lender is blocked on.
+pub fn call_lease(lender: &task::Task, index: u8) -> Option<abi::Lease> {
+    let input = lender.context().sys_registers().input::<SysCallInput>();
+
+    if index >= input.num_leases {
+        return None;
+    }
+
+    // Safety: The lease table was checked to be aligned and within the
+    // lender's memory when the SYS_CALL was made.
+    let lease = unsafe { *(input.leases as *const abi::Lease).add(index as usize) };
+    Some(lease)
+}
+
+pub fn set_call_result(
+    target: &mut task::Task,
+    caller: Option<&task::Task>,
+    status: abi::IpcStatus,
+) {
+    let out_capacity = target
+        .context()
+        .sys_registers()
+        .input::<SysCallInput>()
+        .out_capacity;
 
     let target_output = target
         .context_mut()
         .sys_registers_mut()
         .output::<SysCallOutput>();
 
-    let len = out_capacity.min(in_len) as usize;
+    target_output.status = status;
+
+    if let Some(sender) = caller {
+        let caller_input = sender.context().sys_registers().input::<SysSendInput>();
+        let in_len = caller_input.in_len;
 
-    target_output.len = len as u8;
-    target_output.data[..len].copy_from_slice(&caller_input.data[..len]);
+        let len = out_capacity.min(in_len) as usize;
+
+        target_output.len = len as u8;
+        target_output.data[..len].copy_from_slice(&caller_input.data[..len]);
+    } else {
+        target_output.len = 0;
+    }
 }
 
 #[repr(C)]
 #[derive(AsBytes, FromBytes, FromZeroes)]
 struct SysNotifyInput {
     target: u8,
-    _pad: [u8; 3],
+    _pad: [u8; size_of::<usize>() - 1],
     notifications: u32,
 }
 
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysNotifyOutput {
+    status: abi::IpcStatus,
+    _pad: [u8; size_of::<usize>() - 1],
+}
+
 // Notify another task asynchronously.
-// fn SYS_NOTIFY(target: u8, notifications: u32)
+// fn SYS_NOTIFY(target: u8, notifications: u32) -> (status: u8)
 fn do_sys_notify(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
     let caller = &mut task_table[caller_idx];
     let input = caller.context().sys_registers().input::<SysNotifyInput>();
@@ -199,21 +315,32 @@ fn do_sys_notify(task_table: &mut task::TaskTable, caller_idx: task::TaskId) ->
         let notifications = input.notifications;
         task::do_notify(task_table, caller_idx, target_idx, notifications)
     } else {
-        task::do_panic(task_table, caller_idx)
+        set_notify_result(caller, abi::IpcStatus::InvalidTarget);
+        task::Schedule::Same
     }
 }
 
+pub fn set_notify_result(caller: &mut task::Task, status: abi::IpcStatus) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysNotifyOutput>();
+
+    output.status = status;
+}
+
 #[repr(C)]
 #[derive(AsBytes, FromBytes, FromZeroes)]
 struct SysRequestTimerInput {
     periodic: u8,
-    _pad: [u8; 3],
+    slot: u8,
+    _pad: [u8; size_of::<usize>() - 2],
     deadline: u32,
 }
 
-// Request a timer notification at the given deadline or period, will override
-// any previous timer configuration.
-// fn SYS_SET_TIMER(relative: bool, deadline: u32)
+// Request a notification from a timer slot at the given deadline or period,
+// will override any previous configuration of that slot.
+// fn SYS_SET_TIMER(periodic: bool, slot: u8, deadline: u32)
 fn do_sys_set_timer(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
     let caller = &mut task_table[caller_idx];
     let input = caller
@@ -222,9 +349,65 @@ fn do_sys_set_timer(task_table: &mut task::TaskTable, caller_idx: task::TaskId)
         .input::<SysRequestTimerInput>();
 
     let periodic = input.periodic > 0;
+    let slot = input.slot as usize;
     let deadline = input.deadline;
 
-    task::do_set_timer(task_table, caller_idx, periodic, deadline)
+    task::do_set_timer(task_table, caller_idx, slot, periodic, deadline)
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysSetTimerAbsoluteInput {
+    deadline_low: u32,
+    _pad0: [u8; size_of::<usize>() - 4],
+    deadline_high: u32,
+    _pad1: [u8; size_of::<usize>() - 4],
+    slot: u8,
+    _pad: [u8; size_of::<usize>() - 1],
+}
+
+// Request a notification from a timer slot at an absolute deadline in
+// microseconds since boot, will override any previous configuration of that
+// slot.
+// fn SYS_SET_TIMER_ABSOLUTE(deadline: u64, slot: u8)
+fn do_sys_set_timer_absolute(
+    task_table: &mut task::TaskTable,
+    caller_idx: task::TaskId,
+) -> task::Schedule {
+    let caller = &mut task_table[caller_idx];
+    let input = caller
+        .context()
+        .sys_registers()
+        .input::<SysSetTimerAbsoluteInput>();
+
+    let deadline = ((input.deadline_high as u64) << 32) | (input.deadline_low as u64);
+    let slot = input.slot as usize;
+
+    task::do_set_timer_absolute(task_table, caller_idx, slot, deadline)
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysGetTimeOutput {
+    now_low: u32,
+    _pad0: [u8; size_of::<usize>() - 4],
+    now_high: u32,
+}
+
+// Get the time in microseconds since boot.
+// fn SYS_GET_TIME() -> (now: u64)
+fn do_sys_get_time(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
+    task::do_get_time(task_table, caller_idx)
+}
+
+pub fn set_get_time_result(caller: &mut task::Task, now: u64) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysGetTimeOutput>();
+
+    output.now_low = now as u32;
+    output.now_high = (now >> 32) as u32;
 }
 
 #[repr(C)]
@@ -232,6 +415,7 @@ fn do_sys_set_timer(task_table: &mut task::TaskTable, caller_idx: task::TaskId)
 struct SysInterruptControlInput {
     interrupt: usize,
     control: abi::InterruptControl,
+    _pad: [u8; size_of::<usize>() - 4],
 }
 
 // Enables or disables a given interrupt. After an interrupt has been received
@@ -255,6 +439,431 @@ fn do_sys_interrupt_control(
     }
 }
 
+// Check in with the task's watchdog, restarting its period.
+// fn SYS_HEARTBEAT()
+fn do_sys_heartbeat(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
+    task::do_heartbeat(task_table, caller_idx)
+}
+
+// Give way to any other ready task with the same priority, without blocking.
+// fn SYS_YIELD()
+fn do_sys_yield(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
+    task::do_yield(task_table, caller_idx)
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysRestartTaskInput {
+    target: u8,
+    _pad: [u8; size_of::<usize>() - 1],
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysRestartTaskOutput {
+    restarted: u8,
+    _pad: [u8; size_of::<usize>() - 1],
+}
+
+// Restart a task that is in the fatal state, may only be called by the
+// supervisor task.
+// fn SYS_RESTART_TASK(target: u8) -> (restarted: bool)
+fn do_sys_restart_task(
+    task_table: &mut task::TaskTable,
+    caller_idx: task::TaskId,
+) -> task::Schedule {
+    let caller = &mut task_table[caller_idx];
+    let input = caller
+        .context()
+        .sys_registers()
+        .input::<SysRestartTaskInput>();
+
+    if let Some(target_idx) = task::TaskId::new(input.target) {
+        task::do_restart_task(task_table, caller_idx, target_idx)
+    } else {
+        task::do_panic(task_table, caller_idx)
+    }
+}
+
+pub fn set_restart_task_result(caller: &mut task::Task, restarted: bool) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysRestartTaskOutput>();
+
+    output.restarted = restarted as u8;
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysReadFaultInput {
+    target: u8,
+    _pad: [u8; size_of::<usize>() - 1],
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysReadFaultOutput {
+    valid: u8,
+    _pad: [u8; size_of::<usize>() - 1],
+    record: arch::FaultRecord,
+}
+
+// Read the state captured when a task last took an exception, the record is
+// only valid if that task has faulted since it was last reset.
+// fn SYS_READ_FAULT(target: u8) -> (valid: bool, record: FaultRecord)
+fn do_sys_read_fault(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
+    let caller = &mut task_table[caller_idx];
+    let input = caller
+        .context()
+        .sys_registers()
+        .input::<SysReadFaultInput>();
+
+    if let Some(target_idx) = task::TaskId::new(input.target) {
+        task::do_read_fault(task_table, caller_idx, target_idx)
+    } else {
+        task::do_panic(task_table, caller_idx)
+    }
+}
+
+pub fn set_read_fault_result(caller: &mut task::Task, record: Option<arch::FaultRecord>) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysReadFaultOutput>();
+
+    if let Some(record) = record {
+        output.valid = 1;
+        output.record = record;
+    } else {
+        output.valid = 0;
+    }
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysFaultedTasksOutput {
+    tasks: u32,
+    _pad: [u8; size_of::<usize>() - 4],
+}
+
+// Get the set of tasks that are in the fatal state, bit n is set if task n
+// has faulted and not yet been restarted.
+// fn SYS_FAULTED_TASKS() -> (tasks: u32)
+fn do_sys_faulted_tasks(
+    task_table: &mut task::TaskTable,
+    caller_idx: task::TaskId,
+) -> task::Schedule {
+    task::do_faulted_tasks(task_table, caller_idx)
+}
+
+pub fn set_faulted_tasks_result(caller: &mut task::Task, tasks: u32) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysFaultedTasksOutput>();
+
+    output.tasks = tasks;
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysTimerMissedInput {
+    slot: u8,
+    _pad: [u8; size_of::<usize>() - 1],
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysTimerMissedOutput {
+    missed_periods: u8,
+    _pad: [u8; size_of::<usize>() - 1],
+}
+
+// Take the number of periods of a periodic timer slot that expired without
+// being received, since the count was last taken. The count of slot 0 is also
+// taken by SYS_RECEIVE.
+// fn SYS_TIMER_MISSED(slot: u8) -> (missed_periods: u8)
+fn do_sys_timer_missed(
+    task_table: &mut task::TaskTable,
+    caller_idx: task::TaskId,
+) -> task::Schedule {
+    let input = task_table[caller_idx]
+        .context()
+        .sys_registers()
+        .input::<SysTimerMissedInput>();
+    let slot = input.slot as usize;
+
+    task::do_timer_missed(task_table, caller_idx, slot)
+}
+
+pub fn set_timer_missed_result(caller: &mut task::Task, missed_periods: u8) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysTimerMissedOutput>();
+
+    output.missed_periods = missed_periods;
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysBorrowInfoInput {
+    lender: u8,
+    lease: u8,
+    _pad: [u8; size_of::<usize>() - 2],
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysBorrowInfoOutput {
+    status: abi::IpcStatus,
+    access: abi::LeaseAccess,
+    _pad: [u8; size_of::<usize>() - 2],
+    len: usize,
+}
+
+// Get the access and length of a lease attached to a SYS_CALL that the caller
+// has received but not yet responded to.
+// fn SYS_BORROW_INFO(lender: u8, lease: u8) -> (status: u8, access: u8, len: usize)
+fn do_sys_borrow_info(
+    task_table: &mut task::TaskTable,
+    caller_idx: task::TaskId,
+) -> task::Schedule {
+    let caller = &mut task_table[caller_idx];
+    let input = caller
+        .context()
+        .sys_registers()
+        .input::<SysBorrowInfoInput>();
+
+    if let Some(lender_idx) = task::TaskId::new(input.lender) {
+        let lease = input.lease;
+        task::do_borrow_info(task_table, caller_idx, lender_idx, lease)
+    } else {
+        set_borrow_info_result(caller, Err(abi::IpcStatus::InvalidTarget));
+        task::Schedule::Same
+    }
+}
+
+pub fn set_borrow_info_result(caller: &mut task::Task, result: Result<abi::Lease, abi::IpcStatus>) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysBorrowInfoOutput>();
+
+    match result {
+        Ok(lease) => {
+            output.status = abi::IpcStatus::Success;
+            output.access = lease.access;
+            output.len = lease.len;
+        }
+        Err(status) => {
+            output.status = status;
+            output.len = 0;
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysBorrowInput {
+    lender: u8,
+    lease: u8,
+    _pad: [u8; size_of::<usize>() - 2],
+    offset: usize,
+    buffer: usize,
+    len: usize,
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysBorrowOutput {
+    status: abi::IpcStatus,
+    _pad: [u8; size_of::<usize>() - 1],
+    len: usize,
+}
+
+// Copy from a lease, starting at offset, into the caller's buffer. The number
+// of bytes copied may be less than len if the lease is shorter.
+// fn SYS_BORROW_READ(lender: u8, lease: u8, offset: usize, buffer: *mut u8, len: usize)
+//     -> (status: u8, len: usize)
+//
+// Copy from the caller's buffer into a read-write lease, starting at offset.
+// fn SYS_BORROW_WRITE(lender: u8, lease: u8, offset: usize, buffer: *const u8, len: usize)
+//     -> (status: u8, len: usize)
+fn do_sys_borrow(
+    task_table: &mut task::TaskTable,
+    caller_idx: task::TaskId,
+    direction: task::BorrowDirection,
+) -> task::Schedule {
+    let caller = &mut task_table[caller_idx];
+    let input = caller.context().sys_registers().input::<SysBorrowInput>();
+
+    if let Some(lender_idx) = task::TaskId::new(input.lender) {
+        let lease = input.lease;
+        let offset = input.offset;
+        let buffer = input.buffer;
+        let len = input.len;
+        task::do_borrow(
+            task_table, caller_idx, lender_idx, lease, direction, offset, buffer, len,
+        )
+    } else {
+        set_borrow_result(caller, Err(abi::IpcStatus::InvalidTarget));
+        task::Schedule::Same
+    }
+}
+
+pub fn set_borrow_result(caller: &mut task::Task, result: Result<usize, abi::IpcStatus>) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysBorrowOutput>();
+
+    match result {
+        Ok(len) => {
+            output.status = abi::IpcStatus::Success;
+            output.len = len;
+        }
+        Err(status) => {
+            output.status = status;
+            output.len = 0;
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysTaskInfoInput {
+    target: u8,
+    _pad: [u8; size_of::<usize>() - 1],
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysTaskInfoOutput {
+    status: abi::IpcStatus,
+    state: abi::TaskState,
+    priority: u8,
+    blocked_on: u8,
+    _pad0: [u8; size_of::<usize>() - 4],
+    notifications: u32,
+    _pad1: [u8; size_of::<usize>() - 4],
+    deadline_low: u32,
+    _pad2: [u8; size_of::<usize>() - 4],
+    deadline_high: u32,
+    _pad3: [u8; size_of::<usize>() - 4],
+    stack_size: u32,
+    _pad4: [u8; size_of::<usize>() - 4],
+    stack_used: u32,
+    _pad5: [u8; size_of::<usize>() - 4],
+    // The name is last as the number of registers it spans depends on XLEN.
+    name: [u8; task::MAX_TASK_NAME_LENGTH],
+}
+
+// Read the status of a task, requires the INTROSPECT flag. blocked_on is the
+// target of a SYS_CALL, or u8::MAX, and deadline is u64::MAX if no timer is
+// set. stack_used is the stack high-water mark in bytes. priority is the
+// task's current priority as a rank, where the highest priority configured in
+// app.toml is 0, including any priority inherited from its callers.
+// fn SYS_TASK_INFO(target: u8) -> (status: IpcStatus, state: TaskState, priority: u8,
+//     blocked_on: u8, notifications: u32, deadline: u64, stack_size: u32,
+//     stack_used: u32, name: [u8; 16])
+fn do_sys_task_info(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
+    let caller = &mut task_table[caller_idx];
+    let input = caller.context().sys_registers().input::<SysTaskInfoInput>();
+
+    let target = input.target;
+
+    task::do_task_info(task_table, caller_idx, target)
+}
+
+pub fn set_task_info_result(
+    caller: &mut task::Task,
+    result: Result<task::TaskInfo, abi::IpcStatus>,
+) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysTaskInfoOutput>();
+
+    let info = match result {
+        Ok(info) => info,
+        Err(status) => {
+            output.status = status;
+            return;
+        }
+    };
+
+    let (state, blocked_on) = match info.state {
+        task::TaskState::Fatal => (abi::TaskState::Fatal, u8::MAX),
+        task::TaskState::Ready => (abi::TaskState::Ready, u8::MAX),
+        task::TaskState::CallRequest(target) => (abi::TaskState::CallRequest, target.into()),
+        task::TaskState::CallResponse(target) => (abi::TaskState::CallResponse, target.into()),
+        task::TaskState::Receive => (abi::TaskState::Receive, u8::MAX),
+    };
+
+    let deadline = info.timer_deadline.unwrap_or(u64::MAX);
+
+    output.status = abi::IpcStatus::Success;
+    output.state = state;
+    output.priority = info.current_priority;
+    output.blocked_on = blocked_on;
+    output.notifications = info.notifications;
+    output.deadline_low = deadline as u32;
+    output.deadline_high = (deadline >> 32) as u32;
+    output.stack_size = info.stack_size as u32;
+    output.stack_used = info.stack_used as u32;
+    output.name = *info.name.bytes();
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysReadCrashInput {
+    buffer: usize,
+}
+
+#[repr(C)]
+#[derive(AsBytes, FromBytes, FromZeroes)]
+struct SysReadCrashOutput {
+    status: abi::IpcStatus,
+    valid: u8,
+    _pad: [u8; size_of::<usize>() - 2],
+}
+
+// Take the crash record kept across resets, requires the INTROSPECT flag. If
+// a record is valid it is copied to buffer and cleared, so each crash is only
+// reported once.
+// fn SYS_READ_CRASH(buffer: *mut CrashRecord) -> (status: IpcStatus, valid: bool)
+fn do_sys_read_crash(task_table: &mut task::TaskTable, caller_idx: task::TaskId) -> task::Schedule {
+    let caller = &mut task_table[caller_idx];
+    let input = caller
+        .context()
+        .sys_registers()
+        .input::<SysReadCrashInput>();
+
+    let buffer = input.buffer;
+
+    task::do_read_crash(task_table, caller_idx, buffer)
+}
+
+pub fn set_read_crash_result(caller: &mut task::Task, result: Result<bool, abi::IpcStatus>) {
+    let output = caller
+        .context_mut()
+        .sys_registers_mut()
+        .output::<SysReadCrashOutput>();
+
+    match result {
+        Ok(valid) => {
+            output.status = abi::IpcStatus::Success;
+            output.valid = valid as u8;
+        }
+        Err(status) => {
+            output.status = status;
+            output.valid = 0;
+        }
+    }
+}
+
 pub fn handle_syscall(
     task_table: &mut task::TaskTable,
     caller_idx: task::TaskId,
@@ -262,7 +871,14 @@ pub fn handle_syscall(
     let caller = &mut task_table[caller_idx];
     caller.context_mut().sys_advance_pc();
 
-    match caller.context().sys_registers().id() {
+    let registers = caller.context().sys_registers();
+    trace::record(
+        trace::Event::Syscall,
+        caller_idx.into(),
+        (registers.id().0 as u32 & 0xff) | ((registers.regs[0] as u32 & 0xff) << 8),
+    );
+
+    match registers.id() {
         abi::SysCallId::Panic => do_sys_panic(task_table, caller_idx),
         abi::SysCallId::Receive => do_sys_receive(task_table, caller_idx),
         abi::SysCallId::Send => do_sys_send(task_table, caller_idx),
@@ -270,6 +886,24 @@ pub fn handle_syscall(
         abi::SysCallId::Notify => do_sys_notify(task_table, caller_idx),
         abi::SysCallId::SetTimer => do_sys_set_timer(task_table, caller_idx),
         abi::SysCallId::InterruptControl => do_sys_interrupt_control(task_table, caller_idx),
+        abi::SysCallId::RestartTask => do_sys_restart_task(task_table, caller_idx),
+        abi::SysCallId::ReadFault => do_sys_read_fault(task_table, caller_idx),
+        abi::SysCallId::CallTimeout => do_sys_call_timeout(task_table, caller_idx),
+        abi::SysCallId::BorrowInfo => do_sys_borrow_info(task_table, caller_idx),
+        abi::SysCallId::BorrowRead => {
+            do_sys_borrow(task_table, caller_idx, task::BorrowDirection::Read)
+        }
+        abi::SysCallId::BorrowWrite => {
+            do_sys_borrow(task_table, caller_idx, task::BorrowDirection::Write)
+        }
+        abi::SysCallId::Yield => do_sys_yield(task_table, caller_idx),
+        abi::SysCallId::GetTime => do_sys_get_time(task_table, caller_idx),
+        abi::SysCallId::SetTimerAbsolute => do_sys_set_timer_absolute(task_table, caller_idx),
+        abi::SysCallId::TaskInfo => do_sys_task_info(task_table, caller_idx),
+        abi::SysCallId::ReadCrash => do_sys_read_crash(task_table, caller_idx),
+        abi::SysCallId::Heartbeat => do_sys_heartbeat(task_table, caller_idx),
+        abi::SysCallId::FaultedTasks => do_sys_faulted_tasks(task_table, caller_idx),
+        abi::SysCallId::TimerMissed => do_sys_timer_missed(task_table, caller_idx),
         _ => do_sys_panic(task_table, caller_idx),
     }
 }
@@ -1,5 +1,4 @@
 use core::{
-    array::from_fn,
     num::{NonZeroU32, NonZeroU64},
     panic,
     sync::atomic::{AtomicUsize, Ordering},
@@ -10,8 +9,9 @@ use memoffset::offset_of;
 use paste::paste;
 use rtos_macros::{const_array_from_fn, rtos_import};
 use seq_macro::seq;
+use zerocopy::AsBytes;
 
-use crate::{arch, syscall, time};
+use crate::{arch, crash, syscall, time, trace};
 
 seq!(N in 1..=16 {
     paste! {
@@ -37,9 +37,224 @@ pub const NUM_TASKS: usize = 0;
 #[cfg(not(feature = "num_tasks_defined"))]
 static mut TASK_TABLE: TaskTable = TaskTable([]);
 
+seq!(N in 1..=4 {
+    paste! {
+        #[cfg(feature = "num_timers_" N)]
+        const NUM_TIMERS: usize = N;
+        #[doc(hidden)]
+        #[cfg(feature = "num_timers_" N)]
+        #[export_name = "rtos.feature.num_timers_" N]
+        #[link_section = ".note.rtos.feature"]
+        #[used]
+        static [<RTOS_FEATURE_NUM_TIMERS_ N _MARKER>]: () = ();
+    }
+});
+
+// Without any of the num_timers_N features each task has a single timer.
+#[cfg(not(feature = "num_timers_defined"))]
+const NUM_TIMERS: usize = 1;
+#[doc(hidden)]
+#[cfg(not(feature = "num_timers_defined"))]
+#[export_name = "rtos.feature.num_timers_1"]
+#[link_section = ".note.rtos.feature"]
+#[used]
+static RTOS_FEATURE_NUM_TIMERS_DEFAULT_MARKER: () = ();
+
+const _: () = assert!(NUM_TIMERS <= syscall::abi::MAX_TIMERS);
+
 // The task table begins locked, it is only unlocked once it is initialized.
+// Harts wait for each other to release the lock, so it is not held while idle.
 static TASK_TABLE_LOCK: AtomicUsize = AtomicUsize::new(1);
 
+// Tasks in the Ready state, maintained as tasks change state or priority. This
+// is only accessed through a reference to a task or the task table, which can
+// only exist while TASK_TABLE_LOCK is held.
+static mut READY_QUEUE: ReadyQueue = ReadyQueue::new();
+
+// Tasks in the CallRequest state, maintained alongside READY_QUEUE and
+// accessed under the same conditions.
+static mut CALL_QUEUE: CallQueue = CallQueue::new();
+
+// The set of ready tasks, arranged so that the highest priority ready task can
+// be found in constant time. rtos_app_build compacts task priorities so that
+// every priority is less than NUM_TASKS.
+struct ReadyQueue {
+    // Bit n is set if any task with priority n is ready.
+    priorities: u32,
+    // For each priority, bit n is set if task n is ready with that priority.
+    tasks: [u32; NUM_TASKS],
+    // For each priority, the task index at which to start searching for the
+    // next task to run. This is advanced to share time between tasks with
+    // equal priority.
+    next: [u8; NUM_TASKS],
+    // The task that owns the current time slice on each hart, the deadline at
+    // which the slice ends is held in the timer queue.
+    slice_owner: [Option<TaskId>; arch::NUM_HARTS],
+    // The task running on each hart, if any.
+    running: [Option<TaskId>; arch::NUM_HARTS],
+    // Bit n is set if task n is running on any hart.
+    running_tasks: u32,
+    // For each hart, bit n is set if task n is pinned to another hart.
+    pinned_elsewhere: [u32; arch::NUM_HARTS],
+    // Set when a task is inserted or removed. Cleared each time the task table
+    // is locked, so that other harts are only asked to reschedule if a state or
+    // priority change may have affected them.
+    changed: bool,
+}
+
+impl ReadyQueue {
+    const fn new() -> Self {
+        Self {
+            priorities: 0,
+            tasks: [0; NUM_TASKS],
+            next: [0; NUM_TASKS],
+            slice_owner: [None; arch::NUM_HARTS],
+            running: [None; arch::NUM_HARTS],
+            running_tasks: 0,
+            pinned_elsewhere: [0; arch::NUM_HARTS],
+            changed: false,
+        }
+    }
+
+    fn insert(&mut self, task_idx: TaskId, priority: u8) {
+        self.tasks[priority as usize] |= 1 << task_idx.0;
+        self.priorities |= 1 << priority;
+        self.changed = true;
+    }
+
+    fn remove(&mut self, task_idx: TaskId, priority: u8) {
+        self.tasks[priority as usize] &= !(1 << task_idx.0);
+        if self.tasks[priority as usize] == 0 {
+            self.priorities &= !(1 << priority);
+        }
+        self.changed = true;
+    }
+
+    // Exclude task_idx from every hart other than the one it is pinned to.
+    fn pin(&mut self, task_idx: TaskId, hart: usize) {
+        for (other, pinned) in self.pinned_elsewhere.iter_mut().enumerate() {
+            if other != hart {
+                *pinned |= 1 << task_idx.0;
+            }
+        }
+    }
+
+    fn set_running(&mut self, hart: usize, task_idx: Option<TaskId>) {
+        if let Some(previous_idx) = self.running[hart] {
+            self.running_tasks &= !(1 << previous_idx.0);
+        }
+        if let Some(task_idx) = task_idx {
+            self.running_tasks |= 1 << task_idx.0;
+        }
+        self.running[hart] = task_idx;
+    }
+
+    // The tasks which may not be chosen to run on the given hart, as they are
+    // pinned to, or running on, another hart.
+    fn excluded(&self, hart: usize) -> u32 {
+        let own = self.running[hart].map_or(0, |task_idx| 1 << task_idx.0);
+        (self.running_tasks & !own) | self.pinned_elsewhere[hart]
+    }
+
+    // The highest priority ready task, ignoring any task with its bit set in
+    // exclude.
+    fn first(&self, exclude: u32) -> Option<TaskId> {
+        let mut priorities = self.priorities;
+
+        while priorities != 0 {
+            let priority = priorities.trailing_zeros() as usize;
+            priorities &= priorities - 1;
+
+            let tasks = self.tasks[priority] & !exclude;
+            if tasks == 0 {
+                continue;
+            }
+
+            // Prefer the first ready task at or after next, wrapping around to
+            // the lowest index.
+            let after_next = tasks & !((1 << self.next[priority]) - 1);
+            let tasks = if after_next != 0 { after_next } else { tasks };
+            return Some(TaskId(tasks.trailing_zeros() as usize));
+        }
+
+        None
+    }
+
+    // Returns true if any other task is ready with the same priority.
+    fn has_peer(&self, task_idx: TaskId, priority: u8) -> bool {
+        self.tasks[priority as usize] & !(1 << task_idx.0) != 0
+    }
+
+    // Move task_idx behind any other ready task with the same priority.
+    fn rotate(&mut self, task_idx: TaskId, priority: u8) {
+        self.next[priority as usize] = (task_idx.0 + 1) as u8;
+    }
+
+    fn start_time_slice(&mut self, hart: usize, task_idx: TaskId, time_slice: u64) {
+        self.slice_owner[hart] = Some(task_idx);
+        time::set_deadline(
+            TimerKind::TimeSlice(hart).id(),
+            time::now_ticks().wrapping_add(time_slice),
+        );
+    }
+}
+
+// The set of tasks waiting to deliver a SYS_CALL, arranged like ReadyQueue so
+// that the highest priority task calling a given target can be found in
+// constant time.
+struct CallQueue {
+    // Bit n is set if any task with priority n is waiting to call.
+    priorities: u32,
+    // For each priority, bit n is set if task n is waiting to call with that
+    // priority.
+    tasks: [u32; NUM_TASKS],
+    // For each target, bit n is set if task n is waiting to call it.
+    callers: [u32; NUM_TASKS],
+}
+
+impl CallQueue {
+    const fn new() -> Self {
+        Self {
+            priorities: 0,
+            tasks: [0; NUM_TASKS],
+            callers: [0; NUM_TASKS],
+        }
+    }
+
+    fn insert(&mut self, task_idx: TaskId, target_idx: TaskId, priority: u8) {
+        self.tasks[priority as usize] |= 1 << task_idx.0;
+        self.priorities |= 1 << priority;
+        self.callers[target_idx.0] |= 1 << task_idx.0;
+    }
+
+    fn remove(&mut self, task_idx: TaskId, target_idx: TaskId, priority: u8) {
+        self.tasks[priority as usize] &= !(1 << task_idx.0);
+        if self.tasks[priority as usize] == 0 {
+            self.priorities &= !(1 << priority);
+        }
+        self.callers[target_idx.0] &= !(1 << task_idx.0);
+    }
+
+    // The highest priority task waiting to call target_idx, preferring the
+    // lowest index among tasks with equal priority.
+    fn first(&self, target_idx: TaskId) -> Option<TaskId> {
+        let callers = self.callers[target_idx.0];
+        let mut priorities = if callers != 0 { self.priorities } else { 0 };
+
+        while priorities != 0 {
+            let priority = priorities.trailing_zeros() as usize;
+            priorities &= priorities - 1;
+
+            let tasks = self.tasks[priority] & callers;
+            if tasks != 0 {
+                return Some(TaskId(tasks.trailing_zeros() as usize));
+            }
+        }
+
+        None
+    }
+}
+
 pub struct TaskTable([Task; NUM_TASKS]);
 
 impl core::ops::Index<TaskId> for TaskTable {
@@ -88,6 +303,14 @@ pub enum InterruptControl {
 
 pub struct InvalidInterruptControl;
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BorrowDirection {
+    // Copy from the lease into the borrower.
+    Read,
+    // Copy from the borrower into the lease.
+    Write,
+}
+
 impl TryFrom<syscall::abi::InterruptControl> for InterruptControl {
     type Error = InvalidInterruptControl;
 
@@ -109,8 +332,143 @@ pub struct Task {
     current_priority: u8,
     notifications: u32,
 
-    timer_deadline: u64,
-    timer_period: Option<NonZeroU64>,
+    timers: [Timer; NUM_TIMERS],
+    // The ticks of the watchdog period that remain, as of when the task last
+    // stopped running. The watchdog only counts down while the task runs.
+    watchdog_remaining: u64,
+
+    // The state captured at the last exception taken by this task, if any.
+    fault: Option<arch::FaultRecord>,
+
+    // The hart that this task is running on, if any. A running task may not
+    // be chosen by another hart, and its context belongs to the running hart
+    // until the task traps and its full context is saved.
+    hart: Option<u8>,
+    // Set if the task was faulted or restarted by another hart while it was
+    // running. The running hart stops it at its next trap, completing any
+    // restart.
+    stopped: bool,
+
+    // The floating-point registers, only used if the task has Flags::FPU.
+    #[cfg(feature = "fpu")]
+    fpu: arch::FpuContext,
+}
+
+// The number of timer ids in the time module's timer queue: one for the end of
+// the current time slice on each hart, and for each task one for its call
+// timeout, one for its watchdog and one for each of its timer slots.
+const TIMER_IDS_PER_TASK: usize = 2 + NUM_TIMERS;
+pub const NUM_TIMER_IDS: usize = arch::NUM_HARTS + NUM_TASKS * TIMER_IDS_PER_TASK;
+
+// The owner of a deadline in the timer queue.
+#[derive(Clone, Copy)]
+enum TimerKind {
+    TimeSlice(usize),
+    CallTimeout(TaskId),
+    Watchdog(TaskId),
+    Timer(TaskId, usize),
+}
+
+impl TimerKind {
+    fn id(self) -> usize {
+        const BASE: usize = arch::NUM_HARTS;
+
+        match self {
+            TimerKind::TimeSlice(hart) => hart,
+            TimerKind::CallTimeout(task_idx) => BASE + task_idx.0 * TIMER_IDS_PER_TASK,
+            TimerKind::Watchdog(task_idx) => BASE + 1 + task_idx.0 * TIMER_IDS_PER_TASK,
+            TimerKind::Timer(task_idx, slot) => BASE + 2 + task_idx.0 * TIMER_IDS_PER_TASK + slot,
+        }
+    }
+
+    fn from_id(id: usize) -> Self {
+        if id < arch::NUM_HARTS {
+            return TimerKind::TimeSlice(id);
+        }
+
+        let id = id - arch::NUM_HARTS;
+        let task_idx = TaskId(id / TIMER_IDS_PER_TASK);
+        match id % TIMER_IDS_PER_TASK {
+            0 => TimerKind::CallTimeout(task_idx),
+            1 => TimerKind::Watchdog(task_idx),
+            slot => TimerKind::Timer(task_idx, slot - 2),
+        }
+    }
+}
+
+// A single timer slot belonging to a task, the deadline itself is held in the
+// timer queue.
+#[derive(Clone, Copy)]
+struct Timer {
+    period: Option<NonZeroU64>,
+    // The number of periods of a periodic timer that expired without the task
+    // receiving the notification, saturating at u8::MAX.
+    missed: u8,
+}
+
+impl Timer {
+    const fn zeroed() -> Self {
+        Self {
+            period: None,
+            missed: 0,
+        }
+    }
+
+    fn set(&mut self, id: usize, periodic: bool, deadline: Option<NonZeroU32>) {
+        self.missed = 0;
+
+        match (periodic, deadline) {
+            (_, None) => {
+                // Disable the timer.
+                self.period = None;
+                time::cancel_deadline(id);
+            }
+            (true, Some(deadline)) => {
+                // Periodic timer
+                let period_ticks = time::us_to_ticks(deadline.into());
+                self.period = Some(NonZeroU64::new(period_ticks).unwrap());
+                time::set_deadline(id, time::now_ticks().wrapping_add(period_ticks));
+            }
+            (false, Some(deadline)) => {
+                // Relative timer
+                self.period = None;
+                time::set_deadline(
+                    id,
+                    time::now_ticks().wrapping_add(time::us_to_ticks(deadline.into())),
+                );
+            }
+        }
+    }
+
+    fn set_absolute(&mut self, id: usize, deadline_ticks: u64) {
+        self.missed = 0;
+        self.period = None;
+        time::set_deadline(id, deadline_ticks);
+    }
+
+    // Called once the deadline of this timer has passed, unreceived is true
+    // if the previous expiration of this timer has not yet been received.
+    fn expire(&mut self, id: usize, deadline: u64, now_ticks: u64, unreceived: bool) {
+        if let Some(period_ticks) = self.period {
+            // This is a periodic timer that should be rearmed. The next
+            // deadline is anchored to this one so that a late evaluation
+            // doesn't delay every later expiration. Any whole periods that
+            // have already passed are skipped and counted as missed.
+            let period_ticks: u64 = period_ticks.into();
+            let skipped = (now_ticks - deadline) / period_ticks;
+            time::set_deadline(
+                id,
+                deadline.wrapping_add((skipped + 1).wrapping_mul(period_ticks)),
+            );
+
+            // If the previous expiration has not been received then it is
+            // also missed, as the notifications are merged.
+            let missed = (skipped + unreceived as u64).min(u8::MAX as u64) as u8;
+            self.missed = self.missed.saturating_add(missed);
+        }
+
+        // Otherwise this timer was a one shot that won't fire again.
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -153,8 +511,13 @@ impl Task {
             state: TaskState::Fatal,
             current_priority: 0,
             notifications: 0,
-            timer_deadline: 0,
-            timer_period: None,
+            timers: [Timer::zeroed(); NUM_TIMERS],
+            watchdog_remaining: 0,
+            fault: None,
+            hart: None,
+            stopped: false,
+            #[cfg(feature = "fpu")]
+            fpu: arch::FpuContext::zeroed(),
         }
     }
 
@@ -173,6 +536,18 @@ impl Task {
         &mut self.context
     }
 
+    #[cfg(feature = "fpu")]
+    #[inline]
+    pub fn fpu_context(&self) -> &arch::FpuContext {
+        &self.fpu
+    }
+
+    #[cfg(feature = "fpu")]
+    #[inline]
+    pub fn fpu_context_mut(&mut self) -> &mut arch::FpuContext {
+        &mut self.fpu
+    }
+
     pub fn descriptor(&self) -> &'static TaskDescriptor {
         #[rtos_import]
         static TASK_DESCRIPTOR_TABLE: [TaskDescriptor; NUM_TASKS];
@@ -184,6 +559,39 @@ impl Task {
         unsafe { &TASK_DESCRIPTOR_TABLE[self.index().0] }
     }
 
+    // The number of bytes of stack the task has used, found by scanning up
+    // from the bottom of the stack for the first word which no longer holds
+    // STACK_PAINT. If the task does not paint its stack this is the whole
+    // stack.
+    pub fn stack_used(&self) -> usize {
+        let stack_start = usize::from(self.descriptor().stack_start);
+        let stack_end = usize::from(self.descriptor().stack_end);
+
+        let mut addr = stack_start;
+        // Safety: The stack is word aligned RAM within the task's memory
+        // region, which the kernel may always read. The task may be writing
+        // to it, so the read is volatile.
+        while addr < stack_end && unsafe { (addr as *const u32).read_volatile() } == STACK_PAINT {
+            addr += 4;
+        }
+
+        stack_end - addr
+    }
+
+    #[inline]
+    pub fn stack_size(&self) -> usize {
+        usize::from(self.descriptor().stack_end) - usize::from(self.descriptor().stack_start)
+    }
+
+    pub fn name(&self) -> &'static TaskName {
+        #[rtos_import]
+        static TASK_NAME_TABLE: [TaskName; NUM_TASKS];
+
+        // Safety: As above, we trust that TASK_NAME_TABLE is of the correct
+        // size and alignment.
+        unsafe { &TASK_NAME_TABLE[self.index().0] }
+    }
+
     #[inline]
     pub fn state(&self) -> TaskState {
         self.state
@@ -191,6 +599,8 @@ impl Task {
 
     #[inline]
     fn set_state(&mut self, new_state: TaskState) {
+        let previous_state = self.state;
+
         match (self.state, new_state) {
             // The Fatal state may be entered from any other state.
             (_, TaskState::Fatal) => self.state = new_state,
@@ -208,6 +618,9 @@ impl Task {
             // It is legal to return to the Ready state from the CallResponse
             // state.
             (TaskState::CallResponse(_), TaskState::Ready) => self.state = new_state,
+            // It is legal to return to the Ready state from the CallRequest
+            // state if the SYS_CALL timed out or the target died.
+            (TaskState::CallRequest(_), TaskState::Ready) => self.state = new_state,
 
             // It is only legal to enter the Receive state from the Ready
             // state.
@@ -218,15 +631,115 @@ impl Task {
             // All other state transitions are illegal.
             (_, _) => panic!("attempted illegal state transition",),
         }
+
+        self.update_queues(previous_state, self.current_priority);
+
+        // A task which faults while running on another hart must be stopped
+        // by that hart.
+        if new_state == TaskState::Fatal && self.is_running_elsewhere() {
+            self.stopped = true;
+        }
+
+        // The watchdog only runs while the task is ready, a task which is
+        // blocked waiting for a message or a reply is not stuck. Becoming
+        // ready counts as checking in, but the period only counts down once
+        // the task is running.
+        match (
+            previous_state == TaskState::Ready,
+            new_state == TaskState::Ready,
+        ) {
+            (false, true) => self.kick_watchdog(),
+            (true, false) => time::cancel_deadline(TimerKind::Watchdog(self.index()).id()),
+            _ => {}
+        }
+    }
+
+    // Restart the watchdog period, if the task has a watchdog.
+    fn kick_watchdog(&mut self) {
+        self.watchdog_remaining = match self.descriptor().watchdog_us {
+            0 => 0,
+            period => time::us_to_ticks(period),
+        };
+
+        if self.hart.is_some() {
+            self.start_watchdog();
+        }
+    }
+
+    // Count down the remaining watchdog period while the task runs.
+    fn start_watchdog(&mut self) {
+        let id = TimerKind::Watchdog(self.index()).id();
+        if self.descriptor().watchdog_us != 0 && self.state == TaskState::Ready {
+            time::set_deadline(id, time::now_ticks().wrapping_add(self.watchdog_remaining));
+        } else {
+            time::cancel_deadline(id);
+        }
+    }
+
+    // Hold the remaining watchdog period while the task is not running, so a
+    // task starved by higher priority tasks is not considered stuck.
+    fn pause_watchdog(&mut self) {
+        let id = TimerKind::Watchdog(self.index()).id();
+        if let Some(deadline) = time::deadline(id) {
+            self.watchdog_remaining = deadline.saturating_sub(time::now_ticks());
+            time::cancel_deadline(id);
+        }
+    }
+
+    fn set_current_priority(&mut self, priority: u8) {
+        let previous_priority = self.current_priority;
+        self.current_priority = priority;
+
+        self.update_queues(self.state, previous_priority);
+    }
+
+    // Keep READY_QUEUE and CALL_QUEUE consistent with a change to this task's
+    // state or priority.
+    fn update_queues(&mut self, previous_state: TaskState, previous_priority: u8) {
+        // Safety: We hold a mutable reference to a task, so TASK_TABLE_LOCK
+        // must be held and nothing else may be accessing READY_QUEUE or
+        // CALL_QUEUE.
+        let (ready_queue, call_queue) = unsafe { (&mut READY_QUEUE, &mut CALL_QUEUE) };
+
+        match previous_state {
+            TaskState::Ready => ready_queue.remove(self.index(), previous_priority),
+            TaskState::CallRequest(target_idx) => {
+                call_queue.remove(self.index(), target_idx, previous_priority)
+            }
+            _ => {}
+        }
+
+        match self.state {
+            TaskState::Ready => ready_queue.insert(self.index(), self.current_priority),
+            TaskState::CallRequest(target_idx) => {
+                call_queue.insert(self.index(), target_idx, self.current_priority)
+            }
+            _ => {}
+        }
     }
 
     pub fn reset(&mut self) {
         // It is legal for a task to reset from any state, including the Fatal
         // state.
+        let previous_state = self.state;
+        let previous_priority = self.current_priority;
         self.state = TaskState::Ready;
         self.current_priority = self.descriptor().priority;
-        self.context.task_reset(self.descriptor());
-        self.set_timer(false, None);
+        self.update_queues(previous_state, previous_priority);
+        self.notifications = 0;
+        self.fault = None;
+        self.set_call_timeout(None);
+        self.kick_watchdog();
+        // The context of a task running on another hart is only reset once
+        // that hart has stopped it.
+        if self.is_running_elsewhere() {
+            self.stopped = true;
+        } else {
+            self.reset_context();
+        }
+        for slot in 0..NUM_TIMERS {
+            self.set_timer(slot, false, None);
+        }
 
         // Ensure that any interrupts associated with this task are in their
         // initial state.
@@ -237,6 +750,17 @@ impl Task {
         }
     }
 
+    #[inline]
+    pub fn fault(&self) -> Option<&arch::FaultRecord> {
+        self.fault.as_ref()
+    }
+
+    // Take the count of missed periodic expirations of a timer slot.
+    #[inline]
+    pub fn take_timer_missed(&mut self, slot: usize) -> u8 {
+        core::mem::replace(&mut self.timers[slot].missed, 0)
+    }
+
     #[inline]
     pub fn notifications(&self) -> u32 {
         self.notifications
@@ -247,8 +771,39 @@ impl Task {
         self.notifications = 0;
     }
 
-    pub fn set_as_current(&self) {
+    pub fn set_as_current(&mut self) {
+        let hart = arch::hart_id();
+
         assert!(self.state() == TaskState::Ready);
+        assert!(self.can_run_on(hart));
+
+        // Start a new time slice when switching to this task, this only has
+        // an effect if another task with the same priority becomes ready.
+        if let Some(time_slice) = time::time_slice_ticks() {
+            // Safety: We hold a mutable reference to a task, so
+            // TASK_TABLE_LOCK must be held and nothing else may be accessing
+            // READY_QUEUE.
+            let ready_queue = unsafe { &mut READY_QUEUE };
+
+            if ready_queue.slice_owner[hart] != Some(self.index()) {
+                ready_queue.start_time_slice(hart, self.index(), time_slice);
+            }
+        }
+
+        trace::record_switch(self.index().into());
+
+        // A task resumed after an interrupt was never stopped, its watchdog is
+        // already counting down.
+        if self.hart.is_none() {
+            self.hart = Some(hart as u8);
+            self.start_watchdog();
+
+            // Safety: We hold a mutable reference to a task, so
+            // TASK_TABLE_LOCK must be held and nothing else may be accessing
+            // READY_QUEUE.
+            let ready_queue = unsafe { &mut READY_QUEUE };
+            ready_queue.set_running(hart, Some(self.index()));
+        }
 
         arch::apply_memory_protection(self);
         // Safety: This aliases self however the aliased pointer is not used
@@ -256,51 +811,87 @@ impl Task {
         unsafe { arch::set_current_task(self) }
     }
 
-    fn set_timer(&mut self, periodic: bool, deadline: Option<NonZeroU32>) {
-        match (periodic, deadline) {
-            (_, None) => {
-                // Disable the timer. The physical timer may still fire but
-                // that's okay.
-                self.timer_period = None;
-                self.timer_deadline = u64::MAX;
-            }
-            (true, Some(deadline)) => {
-                // Periodic timer
-                let period_ticks = time::us_to_ticks(deadline.into());
-                self.timer_period = Some(NonZeroU64::new(period_ticks).unwrap());
-                self.timer_deadline = time::now_ticks().wrapping_add(period_ticks);
+    // Returns true if the task may be chosen to run on the given hart: it is
+    // not pinned to, or running on, another hart.
+    pub fn can_run_on(&self, hart: usize) -> bool {
+        let pinned = self.descriptor().hart;
+        let running = self.hart.map_or(true, |running| running as usize == hart);
+        running && (pinned == ANY_HART || pinned as usize == hart)
+    }
 
-                time::update_deadline(self.timer_deadline);
-            }
-            (false, Some(deadline)) => {
-                // Relative timer
-                self.timer_period = None;
-                self.timer_deadline =
-                    time::now_ticks().wrapping_add(time::us_to_ticks(deadline.into()));
+    fn is_running_elsewhere(&self) -> bool {
+        self.hart
+            .is_some_and(|running| running as usize != arch::hart_id())
+    }
 
-                time::update_deadline(self.timer_deadline);
-            }
+    // Called by the hart running this task once the task has trapped and its
+    // full context is saved. Returns true if the task was stopped by another
+    // hart while it was running, in which case the trap must be discarded.
+    pub fn stop_running(&mut self) -> bool {
+        if let Some(hart) = self.hart.take() {
+            // Safety: We hold a mutable reference to a task, so
+            // TASK_TABLE_LOCK must be held and nothing else may be accessing
+            // READY_QUEUE.
+            let ready_queue = unsafe { &mut READY_QUEUE };
+            ready_queue.set_running(hart as usize, None);
         }
-    }
+        self.pause_watchdog();
 
-    fn evaluate_timer(&mut self, now_ticks: u64) -> bool {
-        if now_ticks < self.timer_deadline {
-            // The timer has not yet expired.
+        if !core::mem::replace(&mut self.stopped, false) {
             return false;
         }
 
-        if let Some(period_ticks) = self.timer_period {
-            // This is a periodic timer that should be rearmed. The period is
-            // best effort, ie. period_ticks is the minimum period at which the
-            // timer will fire.
-            self.timer_deadline = now_ticks.wrapping_add(period_ticks.into());
-            time::update_deadline(self.timer_deadline);
-        } else {
-            // Otherwise this timer was a one shot that won't fire again.
-            self.timer_deadline = u64::MAX;
+        // The task was restarted, complete the reset of its context.
+        if self.state == TaskState::Ready {
+            self.reset_context();
         }
 
-        return self.post(syscall::abi::SYS_NOTIFICATION_TIMER);
+        true
+    }
+
+    // Return the task to its initial register state, including its FP state.
+    fn reset_context(&mut self) {
+        self.context.task_reset(self.descriptor());
+        #[cfg(feature = "fpu")]
+        arch::reset_fpu(self);
+    }
+
+    // Returns true if the task was stopped by another hart, but is still
+    // running as its full context has not been saved.
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    fn set_timer(&mut self, slot: usize, periodic: bool, deadline: Option<NonZeroU32>) {
+        let id = TimerKind::Timer(self.index(), slot).id();
+        self.timers[slot].set(id, periodic, deadline);
+    }
+
+    fn set_timer_absolute(&mut self, slot: usize, deadline_ticks: u64) {
+        let id = TimerKind::Timer(self.index(), slot).id();
+        self.timers[slot].set_absolute(id, deadline_ticks);
+    }
+
+    fn expire_timer(&mut self, slot: usize, deadline: u64, now_ticks: u64) -> bool {
+        let id = TimerKind::Timer(self.index(), slot).id();
+        let notification = syscall::abi::sys_notification_timer(slot);
+        let unreceived = self.notifications & notification != 0;
+        self.timers[slot].expire(id, deadline, now_ticks, unreceived);
+
+        return self.post(notification);
+    }
+
+    fn set_call_timeout(&mut self, timeout: Option<NonZeroU32>) {
+        let id = TimerKind::CallTimeout(self.index()).id();
+        if let Some(timeout) = timeout {
+            time::set_deadline(
+                id,
+                time::now_ticks().wrapping_add(time::us_to_ticks(timeout.into())),
+            );
+        } else {
+            time::cancel_deadline(id);
+        }
     }
 
     pub fn post(&mut self, notification: u32) -> bool {
@@ -345,17 +936,21 @@ pub fn task_init() {
         // TASK_TABLE_LOCK starts with the lock taken and we ensure
         // init_task_table is only called once with TASK_TABLE_INITIALIZED.
         let table = unsafe { &mut TASK_TABLE };
+        // Safety: As above, TASK_TABLE_LOCK is held.
+        let ready_queue = unsafe { &mut READY_QUEUE };
         for (idx, task) in table.0.iter_mut().enumerate() {
             task.index = idx as u8;
 
+            let pinned = task.descriptor().hart;
+            if pinned != ANY_HART {
+                ready_queue.pin(task.index(), pinned as usize);
+            }
+
             // This task should be set to the Ready state on startup.
             if task.descriptor().flags.contains(Flags::BOOT) {
                 task.reset();
             }
         }
-
-        // Set the highest priority task as the current task.
-        get_preferred_task(table).set_as_current();
     };
 
     // Clear the task table lock now that initialization is complete.
@@ -366,8 +961,14 @@ pub fn with_task_table<F, T>(f: F) -> T
 where
     F: FnOnce(&mut TaskTable) -> T,
 {
-    if TASK_TABLE_LOCK.swap(1, Ordering::Acquire) > 0 {
-        panic!();
+    while TASK_TABLE_LOCK.swap(1, Ordering::Acquire) > 0 {
+        // With a single hart the lock can only be taken if the kernel has
+        // re-entered itself.
+        if arch::NUM_HARTS == 1 {
+            panic!();
+        }
+
+        core::hint::spin_loop();
     }
 
     // Create the reference and call the closure within a block, this ensures
@@ -376,7 +977,26 @@ where
         // Safety: We ensure that there is no concurrent reference to TASK_TABLE
         // with TASK_TABLE_LOCK, so we can safely take a mutable reference.
         let table = unsafe { &mut TASK_TABLE };
-        f(table)
+
+        // Safety: TASK_TABLE_LOCK is held, and the closure only accesses
+        // READY_QUEUE through the task table.
+        let (hart, running) = unsafe {
+            let hart = arch::hart_id();
+            READY_QUEUE.changed = false;
+            (hart, READY_QUEUE.running[hart])
+        };
+
+        let result = f(table);
+
+        // A task becoming ready, blocking or changing priority, or this hart
+        // switching task, may affect which task other harts should be running.
+        // Safety: As above.
+        let changed = unsafe { READY_QUEUE.changed || READY_QUEUE.running[hart] != running };
+        if changed {
+            reschedule_other_harts(table);
+        }
+
+        result
     };
 
     TASK_TABLE_LOCK.store(0, Ordering::Release);
@@ -384,31 +1004,52 @@ where
     result
 }
 
-fn priority_scan<F, T>(task_table: &mut TaskTable, f: &mut F) -> Option<T>
-where
-    F: FnMut(&mut Task) -> Option<T>,
-{
-    // Get an array of task indices sorted by current priority.
-    let mut task_idx_by_priority: [_; NUM_TASKS] = from_fn(|i| TaskId::new(i as u8).unwrap());
-    task_idx_by_priority.sort_unstable_by_key(|i| task_table[*i].current_priority);
+// Get the highest priority ready task which may run on the given hart.
+fn get_preferred_task(hart: usize) -> Option<TaskId> {
+    // Safety: Only called with TASK_TABLE_LOCK held, so nothing else may be
+    // accessing READY_QUEUE.
+    let ready_queue = unsafe { &READY_QUEUE };
+    ready_queue.first(ready_queue.excluded(hart))
+}
 
-    for idx in task_idx_by_priority {
-        if let Some(result) = f(&mut task_table[idx]) {
-            return Some(result);
+// Set the highest priority ready task which may run on this hart as the
+// current task, returning false if there is no such task.
+pub fn set_preferred_task(task_table: &mut TaskTable) -> bool {
+    match get_preferred_task(arch::hart_id()) {
+        Some(task_idx) => {
+            task_table[task_idx].set_as_current();
+            true
         }
+        None => false,
     }
-
-    None
 }
 
-pub fn get_preferred_task(task_table: &mut TaskTable) -> &mut Task {
-    let next_task_idx = priority_scan(task_table, &mut |task| match task.state {
-        TaskState::Ready => Some(task.index()),
-        _ => None,
-    })
-    .expect("No runnable task.");
+// Request a reschedule on any other hart which is running a task that has been
+// stopped, or which may run a ready task with a higher priority than its
+// current task.
+fn reschedule_other_harts(task_table: &TaskTable) {
+    let this_hart = arch::hart_id();
+
+    // Safety: We hold a reference to the task table, so TASK_TABLE_LOCK must
+    // be held and nothing else may be accessing READY_QUEUE.
+    let ready_queue = unsafe { &READY_QUEUE };
+
+    for hart in (0..arch::NUM_HARTS).filter(|&hart| hart != this_hart) {
+        let current = ready_queue.running[hart].map(|task_idx| &task_table[task_idx]);
 
-    &mut task_table[next_task_idx]
+        let reschedule = match (current, get_preferred_task(hart)) {
+            (Some(current), _) if current.stopped => true,
+            (Some(current), Some(preferred_idx)) => {
+                task_table[preferred_idx].current_priority < current.current_priority
+            }
+            (None, Some(_)) => true,
+            (_, None) => false,
+        };
+
+        if reschedule {
+            arch::reschedule_hart(hart);
+        }
+    }
 }
 
 fn is_valid_target(caller_idx: TaskId, target_idx: TaskId) -> bool {
@@ -420,6 +1061,58 @@ fn is_valid_target(caller_idx: TaskId, target_idx: TaskId) -> bool {
     return true;
 }
 
+// Check that the target of an IPC operation is able to take part in it.
+fn check_target(
+    task_table: &TaskTable,
+    caller_idx: TaskId,
+    target_idx: TaskId,
+) -> Result<(), syscall::abi::IpcStatus> {
+    if !is_valid_target(caller_idx, target_idx) {
+        return Err(syscall::abi::IpcStatus::InvalidTarget);
+    }
+
+    if task_table[target_idx].state() == TaskState::Fatal {
+        return Err(syscall::abi::IpcStatus::TargetDead);
+    }
+
+    Ok(())
+}
+
+// Check that the application configuration permits the caller to use the
+// target with the given mask from the caller's descriptor.
+fn check_permitted(target_idx: TaskId, permitted: u32) -> Result<(), syscall::abi::IpcStatus> {
+    if permitted & (1 << target_idx.0) == 0 {
+        return Err(syscall::abi::IpcStatus::AccessDenied);
+    }
+
+    Ok(())
+}
+
+// Follow the chain of SYS_CALLs from target, returning a bitmask of the tasks
+// in the chain if it leads back to caller.
+fn find_call_cycle(task_table: &TaskTable, caller_idx: TaskId, target_idx: TaskId) -> Option<u32> {
+    let mut cycle = (1 << caller_idx.0) | (1 << target_idx.0);
+    let mut task_idx = target_idx;
+
+    // Any cycle must be found within NUM_TASKS steps, this also bounds the
+    // walk if there is an existing cycle that does not include the caller.
+    for _ in 0..NUM_TASKS {
+        match task_table[task_idx].state() {
+            TaskState::CallRequest(x) | TaskState::CallResponse(x) => {
+                if x == caller_idx {
+                    return Some(cycle);
+                }
+
+                cycle |= 1 << x.0;
+                task_idx = x;
+            }
+            _ => return None,
+        }
+    }
+
+    None
+}
+
 fn inherit_priority(task_table: &mut TaskTable, caller_idx: TaskId, target_idx: TaskId) {
     let mut caller_idx = caller_idx;
     let mut target_idx = target_idx;
@@ -428,9 +1121,9 @@ fn inherit_priority(task_table: &mut TaskTable, caller_idx: TaskId, target_idx:
         let (caller, target) = task_table.get_pair_mut(caller_idx, target_idx);
 
         if caller.current_priority < target.current_priority {
-            target.current_priority = caller.current_priority;
+            target.set_current_priority(caller.current_priority);
 
-            if let TaskState::CallRequest(x) = target.state() {
+            if let TaskState::CallRequest(x) | TaskState::CallResponse(x) = target.state() {
                 caller_idx = target_idx;
                 target_idx = x;
             }
@@ -446,18 +1139,37 @@ fn recalculate_priority(task_table: &mut TaskTable, task_idx: TaskId) -> u8 {
 
     for task in task_table.0.iter() {
         // If some task targeted this task with a SYS_CALL we inherit their
-        // priority.
-        if task.state() == TaskState::CallRequest(task_idx) {
-            priority = priority.min(task.current_priority);
+        // priority, until we have responded.
+        if let TaskState::CallRequest(x) | TaskState::CallResponse(x) = task.state() {
+            if x == task_idx {
+                priority = priority.min(task.current_priority);
+            }
         }
     }
 
     let task = &mut task_table[task_idx];
-    task.current_priority = priority;
+    task.set_current_priority(priority);
 
     priority
 }
 
+// Recalculate the priority of a task, and of every task that it is blocked on
+// through a chain of SYS_CALLs.
+fn recalculate_priority_chain(task_table: &mut TaskTable, task_idx: TaskId) {
+    let mut task_idx = task_idx;
+
+    // A chain can't be longer than the number of tasks, this bounds the walk
+    // even if the chain contains a cycle.
+    for _ in 0..NUM_TASKS {
+        recalculate_priority(task_table, task_idx);
+
+        match task_table[task_idx].state() {
+            TaskState::CallRequest(x) | TaskState::CallResponse(x) => task_idx = x,
+            _ => return,
+        }
+    }
+}
+
 fn deliver_call(caller: &mut Task, target: &mut Task) {
     debug_assert!(caller.state() == TaskState::CallRequest(target.index()));
     debug_assert!(target.state() == TaskState::Receive);
@@ -472,27 +1184,266 @@ fn deliver_call(caller: &mut Task, target: &mut Task) {
     target.set_state(TaskState::Ready);
 }
 
+#[rtos_import]
+static CRITICAL_FAULT_ACTION: CriticalFaultAction;
+
+fn critical_fault(task: &Task) -> ! {
+    // Safety: Reads an immutable constant.
+    match unsafe { CRITICAL_FAULT_ACTION } {
+        CriticalFaultAction::Reset => arch::system_reset(),
+        CriticalFaultAction::Halt => panic!(
+            "critical task {} faulted, {:x?}",
+            u8::from(task.index()),
+            task.fault()
+        ),
+    }
+}
+
+fn find_supervisor(task_table: &TaskTable) -> Option<TaskId> {
+    task_table
+        .0
+        .iter()
+        .find(|task| task.descriptor().flags.contains(Flags::SUPERVISOR))
+        .map(|task| task.index())
+}
+
 pub fn do_panic(task_table: &mut TaskTable, caller_idx: TaskId) -> Schedule {
     let caller = &mut task_table[caller_idx];
+    let previous_state = caller.state();
 
     caller.set_state(TaskState::Fatal);
+
+    // The system can't continue without a critical task.
+    if caller.descriptor().flags.contains(Flags::CRITICAL) {
+        critical_fault(caller);
+    }
+
+    // If we were blocked in a SYS_CALL the target may have inherited our
+    // priority, it must be reverted now that we can no longer be unblocked.
+    if let TaskState::CallRequest(target_idx) | TaskState::CallResponse(target_idx) = previous_state
+    {
+        recalculate_priority_chain(task_table, target_idx);
+    }
+
+    // Any task blocked in a SYS_CALL to this task would never be unblocked,
+    // even if the task is restarted it has lost any request in flight.
+    for task in task_table.0.iter_mut() {
+        if let TaskState::CallRequest(x) | TaskState::CallResponse(x) = task.state() {
+            if x == caller_idx {
+                syscall::set_call_result(task, None, syscall::abi::IpcStatus::TargetDead);
+                task.set_call_timeout(None);
+                task.set_state(TaskState::Ready);
+            }
+        }
+    }
+
+    // Let the supervisor know that some task has faulted, it may then choose
+    // to restart it.
+    if let Some(supervisor_idx) = find_supervisor(task_table) {
+        if supervisor_idx != caller_idx {
+            task_table[supervisor_idx].post(syscall::abi::SYS_NOTIFICATION_FAULT);
+        }
+    }
+
     Schedule::Other
 }
 
+pub fn do_fault(
+    task_table: &mut TaskTable,
+    caller_idx: TaskId,
+    record: arch::FaultRecord,
+) -> Schedule {
+    task_table[caller_idx].fault = Some(record);
+    crash::record_task_fault(&task_table[caller_idx], record);
+
+    do_panic(task_table, caller_idx)
+}
+
+pub fn do_heartbeat(task_table: &mut TaskTable, caller_idx: TaskId) -> Schedule {
+    task_table[caller_idx].kick_watchdog();
+
+    // Checking in cannot cause a reschedule.
+    Schedule::Same
+}
+
+pub fn do_yield(task_table: &mut TaskTable, caller_idx: TaskId) -> Schedule {
+    let caller = &task_table[caller_idx];
+
+    // Safety: We hold a mutable reference to the task table, so
+    // TASK_TABLE_LOCK must be held and nothing else may be accessing
+    // READY_QUEUE.
+    let ready_queue = unsafe { &mut READY_QUEUE };
+
+    // Yielding only gives way to tasks with equal priority, any higher
+    // priority task would already be running.
+    if ready_queue.has_peer(caller_idx, caller.current_priority) {
+        ready_queue.rotate(caller_idx, caller.current_priority);
+        Schedule::Other
+    } else {
+        Schedule::Same
+    }
+}
+
+pub fn do_read_fault(
+    task_table: &mut TaskTable,
+    caller_idx: TaskId,
+    target_idx: TaskId,
+) -> Schedule {
+    let record = task_table[target_idx].fault().copied();
+    syscall::set_read_fault_result(&mut task_table[caller_idx], record);
+
+    // Reading a fault record cannot cause a reschedule.
+    Schedule::Same
+}
+
+pub fn do_faulted_tasks(task_table: &mut TaskTable, caller_idx: TaskId) -> Schedule {
+    let tasks = task_table
+        .0
+        .iter()
+        .filter(|task| task.state() == TaskState::Fatal)
+        .fold(0, |tasks, task| tasks | (1 << task.index().0));
+    syscall::set_faulted_tasks_result(&mut task_table[caller_idx], tasks);
+
+    // Reading the faulted tasks cannot cause a reschedule.
+    Schedule::Same
+}
+
+// A snapshot of the status of a task, as returned by SYS_TASK_INFO.
+pub struct TaskInfo {
+    pub state: TaskState,
+    pub current_priority: u8,
+    pub notifications: u32,
+    // The earliest deadline of any of the task's timers, in microseconds since
+    // boot.
+    pub timer_deadline: Option<u64>,
+    pub name: &'static TaskName,
+    pub stack_size: usize,
+    // The stack high-water mark in bytes.
+    pub stack_used: usize,
+}
+
+pub fn do_task_info(task_table: &mut TaskTable, caller_idx: TaskId, target: u8) -> Schedule {
+    let caller = &task_table[caller_idx];
+
+    let result = if !caller.descriptor().flags.contains(Flags::INTROSPECT) {
+        Err(syscall::abi::IpcStatus::AccessDenied)
+    } else {
+        match TaskId::new(target) {
+            Some(target_idx) => {
+                let target = &task_table[target_idx];
+                let timer_deadline = (0..NUM_TIMERS)
+                    .filter_map(|slot| time::deadline(TimerKind::Timer(target_idx, slot).id()))
+                    .min()
+                    .map(time::ticks_to_us);
+
+                Ok(TaskInfo {
+                    state: target.state(),
+                    current_priority: target.current_priority,
+                    notifications: target.notifications(),
+                    timer_deadline,
+                    name: target.name(),
+                    stack_size: target.stack_size(),
+                    stack_used: target.stack_used(),
+                })
+            }
+            None => Err(syscall::abi::IpcStatus::InvalidTarget),
+        }
+    };
+
+    syscall::set_task_info_result(&mut task_table[caller_idx], result);
+
+    // Reading the status of a task cannot cause a reschedule.
+    Schedule::Same
+}
+
+pub fn do_read_crash(task_table: &mut TaskTable, caller_idx: TaskId, buffer: usize) -> Schedule {
+    let caller = &task_table[caller_idx];
+
+    if !caller.descriptor().flags.contains(Flags::INTROSPECT) {
+        syscall::set_read_crash_result(
+            &mut task_table[caller_idx],
+            Err(syscall::abi::IpcStatus::AccessDenied),
+        );
+        return Schedule::Same;
+    }
+
+    // A buffer outside of the caller's own memory is a bug in the caller.
+    let len = core::mem::size_of::<arch::CrashRecord>();
+    if !arch::can_access(caller, buffer, len, arch::MemoryAccess::Write) {
+        return do_panic(task_table, caller_idx);
+    }
+
+    let valid = match crash::take_record() {
+        Some(record) => {
+            // Safety: The buffer has been checked to be writable by the
+            // caller, which is blocked in the syscall so can't observe the
+            // copy.
+            unsafe {
+                core::ptr::copy_nonoverlapping(record.as_bytes().as_ptr(), buffer as *mut u8, len)
+            };
+            true
+        }
+        None => false,
+    };
+
+    syscall::set_read_crash_result(&mut task_table[caller_idx], Ok(valid));
+
+    // Reading the crash record cannot cause a reschedule.
+    Schedule::Same
+}
+
+pub fn do_restart_task(
+    task_table: &mut TaskTable,
+    caller_idx: TaskId,
+    target_idx: TaskId,
+) -> Schedule {
+    // Only the supervisor may restart other tasks.
+    if !task_table[caller_idx]
+        .descriptor()
+        .flags
+        .contains(Flags::SUPERVISOR)
+    {
+        return do_panic(task_table, caller_idx);
+    }
+
+    if !is_valid_target(caller_idx, target_idx) {
+        return do_panic(task_table, caller_idx);
+    }
+
+    // Only a task in the fatal state may be restarted, restarting any other
+    // task is not an error but has no effect.
+    if task_table[target_idx].state() != TaskState::Fatal {
+        syscall::set_restart_task_result(&mut task_table[caller_idx], false);
+        return Schedule::Same;
+    }
+
+    // Any task that was blocked on the target was failed when it faulted,
+    // so the target is restarted at its base priority.
+    task_table[target_idx].reset();
+
+    let (caller, target) = task_table.get_pair_mut(caller_idx, target_idx);
+    syscall::set_restart_task_result(caller, true);
+
+    if target.current_priority < caller.current_priority {
+        Schedule::Exactly(target_idx)
+    } else {
+        Schedule::Same
+    }
+}
+
 pub fn do_receive(task_table: &mut TaskTable, caller_idx: TaskId) -> Schedule {
     let caller = &mut task_table[caller_idx];
 
     caller.set_state(TaskState::Receive);
 
-    // Scan tasks by priority to see if any task is waiting to call us.
+    // Safety: We hold a mutable reference to the task table, so
+    // TASK_TABLE_LOCK must be held and nothing else may be accessing
+    // CALL_QUEUE.
+    let call_queue = unsafe { &CALL_QUEUE };
+
+    // Find the highest priority task, if any, that is waiting to call us.
     let target_idx = caller_idx;
-    let highest_caller = priority_scan(task_table, &mut |task| {
-        if task.state() == TaskState::CallRequest(target_idx) {
-            Some(task.index())
-        } else {
-            None
-        }
-    });
+    let highest_caller = call_queue.first(target_idx);
 
     if let Some(caller_idx) = highest_caller {
         let (caller, target) = task_table.get_pair_mut(caller_idx, target_idx);
@@ -516,8 +1467,9 @@ pub fn do_receive(task_table: &mut TaskTable, caller_idx: TaskId) -> Schedule {
 }
 
 pub fn do_send(task_table: &mut TaskTable, caller_idx: TaskId, target_idx: TaskId) -> Schedule {
-    if !is_valid_target(caller_idx, target_idx) {
-        return do_panic(task_table, caller_idx);
+    if let Err(status) = check_target(task_table, caller_idx, target_idx) {
+        syscall::set_send_result(&mut task_table[caller_idx], status);
+        return Schedule::Same;
     }
 
     let target = &mut task_table[target_idx];
@@ -527,7 +1479,9 @@ pub fn do_send(task_table: &mut TaskTable, caller_idx: TaskId, target_idx: TaskI
         if blocked_on == caller_idx {
             let (caller, target) = task_table.get_pair_mut(caller_idx, target_idx);
 
-            syscall::set_call_result(target, caller);
+            syscall::set_call_result(target, Some(caller), syscall::abi::IpcStatus::Success);
+            syscall::set_send_result(caller, syscall::abi::IpcStatus::Success);
+            target.set_call_timeout(None);
             target.set_state(TaskState::Ready);
 
             // Our priority may have been raised when the target issued the,
@@ -541,24 +1495,50 @@ pub fn do_send(task_table: &mut TaskTable, caller_idx: TaskId, target_idx: TaskI
                 Schedule::Exactly(target_idx)
             }
         } else {
-            // Attempted to send to a task that was not expecting a
-            // response from this task.
-            return do_panic(task_table, caller_idx);
+            // The target is not expecting a response from this task, it may
+            // have abandoned the SYS_CALL when it timed out so the response
+            // is discarded.
+            let caller = &mut task_table[caller_idx];
+            syscall::set_send_result(caller, syscall::abi::IpcStatus::NotAwaitingReply);
+            Schedule::Same
         }
     } else {
-        // The target is not in the correct state.
-        return do_panic(task_table, caller_idx);
+        // The target is not expecting a response, as above the response is
+        // discarded.
+        let caller = &mut task_table[caller_idx];
+        syscall::set_send_result(caller, syscall::abi::IpcStatus::NotAwaitingReply);
+        Schedule::Same
     }
 }
 
-pub fn do_call(task_table: &mut TaskTable, caller_idx: TaskId, target_idx: TaskId) -> Schedule {
-    if !is_valid_target(caller_idx, target_idx) {
-        return do_panic(task_table, caller_idx);
+pub fn do_call(
+    task_table: &mut TaskTable,
+    caller_idx: TaskId,
+    target_idx: TaskId,
+    timeout: Option<NonZeroU32>,
+) -> Schedule {
+    let uses = task_table[caller_idx].descriptor().uses;
+    if let Err(status) = check_target(task_table, caller_idx, target_idx)
+        .and_then(|_| check_permitted(target_idx, uses))
+    {
+        syscall::set_call_result(&mut task_table[caller_idx], None, status);
+        return Schedule::Same;
+    }
+
+    // If the target is already blocked, directly or indirectly, on the caller
+    // then this call would complete a cycle of tasks that can never be
+    // unblocked. The caller is faulted with a record of the tasks involved.
+    if let Some(cycle) = find_call_cycle(task_table, caller_idx, target_idx) {
+        let record = task_table[caller_idx]
+            .context()
+            .kernel_fault_record(arch::MCAUSE_DEADLOCK, cycle as usize);
+        return do_fault(task_table, caller_idx, record);
     }
 
     let (caller, target) = task_table.get_pair_mut(caller_idx, target_idx);
 
     caller.set_state(TaskState::CallRequest(target_idx));
+    caller.set_call_timeout(timeout);
 
     let schedule = match target.state() {
         // We are currently the highest priority task, and the target just
@@ -580,16 +1560,134 @@ pub fn do_call(task_table: &mut TaskTable, caller_idx: TaskId, target_idx: TaskI
     schedule
 }
 
+// Find a lease attached to the SYS_CALL that the lender is waiting on the
+// caller to respond to.
+fn borrowed_lease(
+    task_table: &TaskTable,
+    caller_idx: TaskId,
+    lender_idx: TaskId,
+    lease_idx: u8,
+) -> Result<syscall::abi::Lease, syscall::abi::IpcStatus> {
+    if !is_valid_target(caller_idx, lender_idx) {
+        return Err(syscall::abi::IpcStatus::InvalidTarget);
+    }
+
+    // Leases are only valid until the caller responds.
+    let lender = &task_table[lender_idx];
+    if lender.state() != TaskState::CallResponse(caller_idx) {
+        return Err(syscall::abi::IpcStatus::NotAwaitingReply);
+    }
+
+    syscall::call_lease(lender, lease_idx).ok_or(syscall::abi::IpcStatus::InvalidLease)
+}
+
+pub fn do_borrow_info(
+    task_table: &mut TaskTable,
+    caller_idx: TaskId,
+    lender_idx: TaskId,
+    lease_idx: u8,
+) -> Schedule {
+    let result = borrowed_lease(task_table, caller_idx, lender_idx, lease_idx);
+    syscall::set_borrow_info_result(&mut task_table[caller_idx], result);
+
+    Schedule::Same
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn do_borrow(
+    task_table: &mut TaskTable,
+    caller_idx: TaskId,
+    lender_idx: TaskId,
+    lease_idx: u8,
+    direction: BorrowDirection,
+    offset: usize,
+    buffer: usize,
+    len: usize,
+) -> Schedule {
+    let lease = match borrowed_lease(task_table, caller_idx, lender_idx, lease_idx) {
+        Ok(lease) => lease,
+        Err(status) => {
+            syscall::set_borrow_result(&mut task_table[caller_idx], Err(status));
+            return Schedule::Same;
+        }
+    };
+
+    let (lender_access, caller_access) = match direction {
+        BorrowDirection::Read => (arch::MemoryAccess::Read, arch::MemoryAccess::Write),
+        BorrowDirection::Write => (arch::MemoryAccess::Write, arch::MemoryAccess::Read),
+    };
+
+    let permitted = match lease.access {
+        syscall::abi::LeaseAccess::Read => direction == BorrowDirection::Read,
+        syscall::abi::LeaseAccess::ReadWrite => true,
+        _ => false,
+    };
+
+    // The lease must permit the access and the lender must own the memory it
+    // describes, we can't trust the lease table as the lender may have
+    // written anything to it.
+    let lease_range = match lease.len.checked_sub(offset) {
+        Some(remaining) if permitted => Some((lease.base.wrapping_add(offset), remaining.min(len))),
+        _ => None,
+    };
+
+    let (lease_base, len) = match lease_range {
+        Some((base, len))
+            if arch::can_access(&task_table[lender_idx], base, len, lender_access) =>
+        {
+            (base, len)
+        }
+        _ => {
+            syscall::set_borrow_result(
+                &mut task_table[caller_idx],
+                Err(syscall::abi::IpcStatus::InvalidLease),
+            );
+            return Schedule::Same;
+        }
+    };
+
+    // A buffer outside of the caller's own memory is a bug in the caller.
+    if !arch::can_access(&task_table[caller_idx], buffer, len, caller_access) {
+        return do_panic(task_table, caller_idx);
+    }
+
+    // Safety: Both ranges have been checked to be accessible to their owning
+    // task, and the lender is blocked so can't observe the copy. A lease may
+    // alias a region shared with the caller so the ranges may overlap.
+    unsafe {
+        match direction {
+            BorrowDirection::Read => {
+                core::ptr::copy(lease_base as *const u8, buffer as *mut u8, len)
+            }
+            BorrowDirection::Write => {
+                core::ptr::copy(buffer as *const u8, lease_base as *mut u8, len)
+            }
+        }
+    }
+
+    syscall::set_borrow_result(&mut task_table[caller_idx], Ok(len));
+    Schedule::Same
+}
+
 pub fn do_notify(
     task_table: &mut TaskTable,
     caller_idx: TaskId,
     target_idx: TaskId,
     notifications: u32,
 ) -> Schedule {
-    if !is_valid_target(caller_idx, target_idx) {
-        return do_panic(task_table, caller_idx);
+    let notifies = task_table[caller_idx].descriptor().notifies;
+    if let Err(status) = check_target(task_table, caller_idx, target_idx)
+        .and_then(|_| check_permitted(target_idx, notifies))
+    {
+        syscall::set_notify_result(&mut task_table[caller_idx], status);
+        return Schedule::Same;
     }
 
+    syscall::set_notify_result(
+        &mut task_table[caller_idx],
+        syscall::abi::IpcStatus::Success,
+    );
+
     let target = &mut task_table[target_idx];
 
     if target.post(notifications) {
@@ -608,24 +1706,66 @@ pub fn do_notify(
 pub fn do_set_timer(
     task_table: &mut TaskTable,
     caller_idx: TaskId,
+    slot: usize,
     periodic: bool,
     deadline: u32,
 ) -> Schedule {
-    // Periodic timers require a non-zero period.
-    if periodic && (deadline == 0) {
+    // Periodic timers require a non-zero period, and the slot must be one
+    // that the kernel is configured with.
+    if (periodic && (deadline == 0)) || (slot >= NUM_TIMERS) {
         return do_panic(task_table, caller_idx);
     }
 
     let caller = &mut task_table[caller_idx];
 
     // A zero deadline cancels the timer, any non zero value sets it.
-    caller.set_timer(periodic, NonZeroU32::new(deadline));
+    caller.set_timer(slot, periodic, NonZeroU32::new(deadline));
 
     // Setting a timer can't itself cause a reschedule - only a later timer
     // interrupt can.
     Schedule::Same
 }
 
+pub fn do_timer_missed(task_table: &mut TaskTable, caller_idx: TaskId, slot: usize) -> Schedule {
+    // The slot must be one that the kernel is configured with.
+    if slot >= NUM_TIMERS {
+        return do_panic(task_table, caller_idx);
+    }
+
+    let caller = &mut task_table[caller_idx];
+    let missed_periods = caller.take_timer_missed(slot);
+    syscall::set_timer_missed_result(caller, missed_periods);
+
+    // Reading the count cannot cause a reschedule.
+    Schedule::Same
+}
+
+pub fn do_set_timer_absolute(
+    task_table: &mut TaskTable,
+    caller_idx: TaskId,
+    slot: usize,
+    deadline_us: u64,
+) -> Schedule {
+    if slot >= NUM_TIMERS {
+        return do_panic(task_table, caller_idx);
+    }
+
+    let caller = &mut task_table[caller_idx];
+
+    // A deadline that has already passed will expire on the next timer
+    // interrupt.
+    caller.set_timer_absolute(slot, time::abs_us_to_ticks(deadline_us));
+
+    // As above, only a later timer interrupt can cause a reschedule.
+    Schedule::Same
+}
+
+pub fn do_get_time(task_table: &mut TaskTable, caller_idx: TaskId) -> Schedule {
+    syscall::set_get_time_result(&mut task_table[caller_idx], time::now_us());
+
+    Schedule::Same
+}
+
 pub fn do_interrupt_control(
     task_table: &mut TaskTable,
     caller_idx: TaskId,
@@ -652,21 +1792,156 @@ pub fn do_interrupt_control(
 pub fn evaluate_timers(task_table: &mut TaskTable, caller_idx: TaskId, now_ticks: u64) -> Schedule {
     let mut current_priority = task_table[caller_idx].current_priority;
     let mut sched = Schedule::Same;
+    let mut slice_expired = false;
+    let mut watchdog_expired = false;
+
+    while let Some((id, deadline)) = time::pop_expired(now_ticks) {
+        let (task_idx, unblocked) = match TimerKind::from_id(id) {
+            TimerKind::TimeSlice(hart) => {
+                trace::record(trace::Event::Timer, 0xff, 0);
+                // The slice of another hart is evaluated immediately, it is
+                // asked to reschedule if its task should give way.
+                if hart == arch::hart_id() {
+                    slice_expired = true;
+                } else {
+                    evaluate_time_slice(task_table, hart);
+                }
+                continue;
+            }
+            TimerKind::CallTimeout(task_idx) => {
+                trace::record(trace::Event::Timer, task_idx.into(), 1);
+                (task_idx, expire_call_timeout(task_table, task_idx))
+            }
+            TimerKind::Watchdog(task_idx) => {
+                trace::record(trace::Event::Timer, task_idx.into(), 2);
+                expire_watchdog(task_table, task_idx);
+                watchdog_expired = true;
+                continue;
+            }
+            TimerKind::Timer(task_idx, slot) => {
+                trace::record(trace::Event::Timer, task_idx.into(), 3 + slot as u32);
+                (
+                    task_idx,
+                    task_table[task_idx].expire_timer(slot, deadline, now_ticks),
+                )
+            }
+        };
 
-    for task in task_table.0.iter_mut() {
-        let unblocked = task.evaluate_timer(now_ticks);
+        let task = &task_table[task_idx];
 
         // If this task was unblocked, is not the current task and has higher
         // priority it should be scheduled.
-        if unblocked && task.index() != caller_idx && task.current_priority < current_priority {
+        if unblocked && task_idx != caller_idx && task.current_priority < current_priority {
             current_priority = task.current_priority;
-            sched = Schedule::Exactly(task.index());
+            sched = Schedule::Exactly(task_idx);
         }
     }
 
+    // Restarting a task may have stopped the caller or unblocked the tasks
+    // that were waiting on it, so a priority scan is needed.
+    if watchdog_expired {
+        return Schedule::Other;
+    }
+
+    if sched == Schedule::Same && slice_expired {
+        sched = evaluate_time_slice(task_table, arch::hart_id());
+    }
+
     sched
 }
 
+fn evaluate_time_slice(task_table: &mut TaskTable, hart: usize) -> Schedule {
+    let time_slice = match time::time_slice_ticks() {
+        Some(time_slice) => time_slice,
+        None => return Schedule::Same,
+    };
+
+    // Safety: We hold a mutable reference to the task table, so
+    // TASK_TABLE_LOCK must be held and nothing else may be accessing
+    // READY_QUEUE.
+    let ready_queue = unsafe { &mut READY_QUEUE };
+
+    let owner_idx = match ready_queue.slice_owner[hart] {
+        Some(owner_idx) => owner_idx,
+        None => return Schedule::Same,
+    };
+
+    // The slice only applies while its owner is running on the hart, if the
+    // owner runs again it starts a new slice.
+    let owner = &task_table[owner_idx];
+    if owner.state() != TaskState::Ready || owner.hart != Some(hart as u8) {
+        ready_queue.slice_owner[hart] = None;
+        return Schedule::Same;
+    }
+
+    if ready_queue.has_peer(owner_idx, owner.current_priority) {
+        // The slice has ended, give way to the next task with equal priority
+        // which will start a new slice.
+        ready_queue.rotate(owner_idx, owner.current_priority);
+
+        if hart == arch::hart_id() {
+            Schedule::Other
+        } else {
+            arch::reschedule_hart(hart);
+            Schedule::Same
+        }
+    } else {
+        // There is no other task to share time with, but one may become ready
+        // before the next slice ends.
+        ready_queue.start_time_slice(hart, owner_idx, time_slice);
+        Schedule::Same
+    }
+}
+
+// A task that ran for its whole watchdog period without checking in is stuck.
+// It is restarted, or the system is reset if the task is critical.
+fn expire_watchdog(task_table: &mut TaskTable, task_idx: TaskId) {
+    let task = &task_table[task_idx];
+    let record = task.context().kernel_fault_record(arch::MCAUSE_WATCHDOG, 0);
+    crash::record_task_fault(task, record);
+
+    if task.descriptor().flags.contains(Flags::CRITICAL) {
+        arch::system_reset();
+    }
+
+    // Any request the task was handling is lost with its state.
+    for task in task_table.0.iter_mut() {
+        if task.state() == TaskState::CallResponse(task_idx) {
+            syscall::set_call_result(task, None, syscall::abi::IpcStatus::TargetDead);
+            task.set_call_timeout(None);
+            task.set_state(TaskState::Ready);
+        }
+    }
+
+    // The fault record is kept so that the cause of the restart can be read.
+    let task = &mut task_table[task_idx];
+    task.reset();
+    task.fault = Some(record);
+
+    // Tasks that are still waiting to call the task must be able to raise
+    // its priority.
+    recalculate_priority(task_table, task_idx);
+}
+
+fn expire_call_timeout(task_table: &mut TaskTable, task_idx: TaskId) -> bool {
+    let task = &mut task_table[task_idx];
+
+    let target_idx = match task.state() {
+        TaskState::CallRequest(x) | TaskState::CallResponse(x) => x,
+        _ => return false,
+    };
+
+    // Abandon the call, any response from the target will be discarded.
+    syscall::set_call_result(task, None, syscall::abi::IpcStatus::Timeout);
+    task.set_state(TaskState::Ready);
+
+    // The target, and anything it is blocked on, may have inherited our
+    // priority which must now be reverted.
+    recalculate_priority_chain(task_table, target_idx);
+
+    true
+}
+
 #[rtos_import]
 static INTERRUPT_DESCRIPTOR_TABLE: InterruptDescriptor;
 #[rtos_import]
@@ -749,6 +2024,8 @@ pub fn handle_interrupt(
     let target_idx = TaskId::new(descriptor.task_id()).unwrap();
     let notification = descriptor.notification();
 
+    trace::record(trace::Event::Interrupt, target_idx.into(), interrupt as u32);
+
     if task_table[target_idx].post(notification) && target_idx != caller_idx {
         let (caller, target) = task_table.get_pair_mut(caller_idx, target_idx);
 
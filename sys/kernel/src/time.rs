@@ -8,6 +8,9 @@ static TIME_US_PER_TICK: u64;
 #[rtos_import]
 static TIME_TICK_FREQUENCY: u32;
 
+#[rtos_import]
+static TIME_SLICE_US: u32;
+
 #[inline]
 fn us_per_tick() -> u64 {
     // Safety: Reads an immutable constant.
@@ -15,7 +18,7 @@ fn us_per_tick() -> u64 {
 }
 
 #[inline]
-fn tick_frequency() -> u32 {
+pub fn tick_frequency() -> u32 {
     // Safety: Reads an immutable constant.
     unsafe { TIME_TICK_FREQUENCY }
 }
@@ -37,6 +40,29 @@ pub fn us_to_ticks(us: u32) -> u64 {
     u64_mul_high(us as u64 * tick_frequency() as u64, u64::MAX / US_PER_S)
 }
 
+// Convert an absolute time in microseconds to ticks, saturating if the time is
+// beyond the range of the tick counter.
+pub fn abs_us_to_ticks(us: u64) -> u64 {
+    const US_PER_S: u128 = 1000000;
+    let ticks = (us as u128 * tick_frequency() as u128) / US_PER_S;
+    ticks.min(u64::MAX as u128) as u64
+}
+
+#[inline]
+pub fn now_us() -> u64 {
+    ticks_to_us(now_ticks())
+}
+
+// The length of a time slice, or None if time slicing is disabled.
+#[inline]
+pub fn time_slice_ticks() -> Option<u64> {
+    // Safety: Reads an immutable constant.
+    match unsafe { TIME_SLICE_US } {
+        0 => None,
+        time_slice => Some(us_to_ticks(time_slice)),
+    }
+}
+
 #[inline]
 pub fn now_ticks() -> u64 {
     arch::now_ticks()
@@ -46,11 +72,189 @@ pub fn handle_timer_expiration(
     task_table: &mut task::TaskTable,
     task_idx: task::TaskId,
 ) -> task::Schedule {
-    task::evaluate_timers(task_table, task_idx, now_ticks())
+    let schedule = task::evaluate_timers(task_table, task_idx, now_ticks());
+
+    // Safety: We hold a mutable reference to the task table, so
+    // TASK_TABLE_LOCK must be held and nothing else may be accessing
+    // TIMER_QUEUE.
+    unsafe { TIMER_QUEUE.update_hardware() };
+
+    schedule
 }
 
-pub fn update_deadline(deadline: u64) {
-    if deadline < arch::timer_deadline() {
-        arch::set_timer_deadline(deadline)
+// Every pending deadline in the kernel, indexed by a timer id allocated by the
+// task module. This is only accessed while TASK_TABLE_LOCK is held.
+static mut TIMER_QUEUE: TimerQueue = TimerQueue::new();
+
+// A min-heap of timer ids ordered by deadline, so that the next deadline can
+// be found in constant time and a deadline can be set or cancelled in
+// logarithmic time.
+struct TimerQueue {
+    // The deadline of each timer id, only valid while it is queued.
+    deadlines: [u64; task::NUM_TIMER_IDS],
+    // The queued timer ids, heap[0] has the earliest deadline.
+    heap: [u8; task::NUM_TIMER_IDS],
+    // One more than the position of each timer id in heap, zero if it is not
+    // queued. This ensures that the queue begins empty when zeroed.
+    position: [u8; task::NUM_TIMER_IDS],
+    len: usize,
+}
+
+impl TimerQueue {
+    const fn new() -> Self {
+        Self {
+            deadlines: [0; task::NUM_TIMER_IDS],
+            heap: [0; task::NUM_TIMER_IDS],
+            position: [0; task::NUM_TIMER_IDS],
+            len: 0,
+        }
+    }
+
+    fn deadline(&self, id: usize) -> Option<u64> {
+        match self.position[id] {
+            0 => None,
+            _ => Some(self.deadlines[id]),
+        }
+    }
+
+    fn peek(&self) -> Option<(usize, u64)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let id = self.heap[0] as usize;
+        Some((id, self.deadlines[id]))
+    }
+
+    fn set(&mut self, id: usize, deadline: u64) {
+        self.deadlines[id] = deadline;
+
+        if self.position[id] == 0 {
+            self.heap[self.len] = id as u8;
+            self.position[id] = (self.len + 1) as u8;
+            self.len += 1;
+        }
+
+        // The deadline may have moved in either direction.
+        self.sift_up(self.position[id] as usize - 1);
+        self.sift_down(self.position[id] as usize - 1);
+    }
+
+    fn remove(&mut self, id: usize) {
+        if self.position[id] == 0 {
+            return;
+        }
+
+        let pos = self.position[id] as usize - 1;
+        self.position[id] = 0;
+        self.len -= 1;
+
+        // Fill the hole with the last entry and restore the heap order.
+        if pos != self.len {
+            let last = self.heap[self.len];
+            self.heap[pos] = last;
+            self.position[last as usize] = (pos + 1) as u8;
+            self.sift_up(pos);
+            self.sift_down(self.position[last as usize] as usize - 1);
+        }
+    }
+
+    fn key(&self, pos: usize) -> u64 {
+        self.deadlines[self.heap[pos] as usize]
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.position[self.heap[a] as usize] = (a + 1) as u8;
+        self.position[self.heap[b] as usize] = (b + 1) as u8;
+    }
+
+    fn sift_up(&mut self, mut pos: usize) {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if self.key(pos) >= self.key(parent) {
+                break;
+            }
+
+            self.swap(pos, parent);
+            pos = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut pos: usize) {
+        loop {
+            let left = 2 * pos + 1;
+            let right = left + 1;
+            let mut smallest = pos;
+
+            if left < self.len && self.key(left) < self.key(smallest) {
+                smallest = left;
+            }
+
+            if right < self.len && self.key(right) < self.key(smallest) {
+                smallest = right;
+            }
+
+            if smallest == pos {
+                break;
+            }
+
+            self.swap(pos, smallest);
+            pos = smallest;
+        }
+    }
+
+    // Program the hardware timer with the earliest deadline, so that it never
+    // fires for a deadline that has been cancelled or moved.
+    fn update_hardware(&self) {
+        let next = self.peek().map_or(u64::MAX, |(_, deadline)| deadline);
+        if next != arch::timer_deadline() {
+            arch::set_timer_deadline(next);
+        }
+    }
+}
+
+// Set, or move, the deadline of a timer id.
+pub fn set_deadline(id: usize, deadline: u64) {
+    // Safety: Deadlines are only changed through a reference to a task or the
+    // task table, so TASK_TABLE_LOCK must be held and nothing else may be
+    // accessing TIMER_QUEUE.
+    let queue = unsafe { &mut TIMER_QUEUE };
+    queue.set(id, deadline);
+    queue.update_hardware();
+}
+
+pub fn cancel_deadline(id: usize) {
+    // Safety: As above.
+    let queue = unsafe { &mut TIMER_QUEUE };
+    queue.remove(id);
+    queue.update_hardware();
+}
+
+// The deadline of a timer id, or None if it is not set.
+pub fn deadline(id: usize) -> Option<u64> {
+    // Safety: As above.
+    unsafe { TIMER_QUEUE.deadline(id) }
+}
+
+// The earliest pending deadline, if any.
+pub fn next_deadline() -> Option<u64> {
+    // Safety: As above.
+    unsafe { TIMER_QUEUE.peek() }.map(|(_, deadline)| deadline)
+}
+
+// Remove and return the earliest timer id with its deadline, if that deadline
+// is at or before now_ticks. The hardware timer is updated once all expired
+// deadlines have been handled.
+pub fn pop_expired(now_ticks: u64) -> Option<(usize, u64)> {
+    // Safety: As above.
+    let queue = unsafe { &mut TIMER_QUEUE };
+
+    match queue.peek() {
+        Some((id, deadline)) if deadline <= now_ticks => {
+            queue.remove(id);
+            Some((id, deadline))
+        }
+        _ => None,
     }
 }
@@ -0,0 +1,160 @@
+// Kernel event tracing. When the kernel is built with the trace feature,
+// timestamped events are recorded into TRACE_BUFFER, a ring buffer in kernel
+// RAM. tools/trace_export reads the buffer back from a memory dump and converts
+// it to a trace which can be opened in Perfetto. Without the feature recording
+// an event compiles to nothing.
+//
+// The buffer is included in the kernel data, so kernel.memory.data must be
+// increased by the size of TraceBuffer when tracing is enabled.
+
+use crate::task;
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum Event {
+    // A trap was handled, arg is the schedule which was chosen: 0 for Same, 1
+    // for Exactly and 2 for Other.
+    Schedule,
+    // The task became the current task.
+    Switch,
+    // The task made a syscall, arg is the syscall id in the low byte and the
+    // low byte of the first argument, the target task for IPC, in the next.
+    Syscall,
+    // An interrupt was posted to the task, arg is the interrupt number.
+    Interrupt,
+    // A deadline expired, arg is 0 for the time slice, 1 for a call timeout,
+    // 2 for a watchdog, or 3 plus the slot for a timer. The task is 0xff for
+    // the time slice.
+    Timer,
+    // No task was ready and the kernel slept.
+    Idle,
+    // The task faulted, arg is the cause.
+    Fault,
+}
+
+impl From<task::Schedule> for u32 {
+    fn from(value: task::Schedule) -> Self {
+        match value {
+            task::Schedule::Same => 0,
+            task::Schedule::Exactly(_) => 1,
+            task::Schedule::Other => 2,
+        }
+    }
+}
+
+#[inline]
+pub fn record(event: Event, task: u8, arg: u32) {
+    #[cfg(feature = "trace")]
+    buffer::record(event, task, arg);
+
+    #[cfg(not(feature = "trace"))]
+    let _ = (event, task, arg);
+}
+
+// Record a switch to a task, only if it is not already the current task.
+#[inline]
+pub fn record_switch(task: u8) {
+    #[cfg(feature = "trace")]
+    buffer::record_switch(task);
+
+    #[cfg(not(feature = "trace"))]
+    let _ = task;
+}
+
+#[cfg(feature = "trace")]
+mod buffer {
+    use super::Event;
+    use crate::{arch, time};
+
+    // "RTRC" in little endian, marks the buffer as initialized for the host.
+    const TRACE_MAGIC: u32 = 0x43525452;
+    const TRACE_CAPACITY: usize = 64;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct TraceEvent {
+        timestamp: u64,
+        kind: u8,
+        task: u8,
+        // The hart which recorded the event.
+        hart: u8,
+        _pad: u8,
+        arg: u32,
+    }
+
+    // The layout of this struct is read by tools/trace_export and must be kept
+    // in sync with it.
+    #[repr(C)]
+    pub struct TraceBuffer {
+        magic: u32,
+        tick_frequency: u32,
+        capacity: u32,
+        // The total number of events recorded, the next event is written to
+        // head % capacity.
+        head: u32,
+        events: [TraceEvent; TRACE_CAPACITY],
+    }
+
+    // This is zero initialized so that it is placed in .bss, the header is
+    // filled in when the first event is recorded. This is only accessed while
+    // TASK_TABLE_LOCK is held.
+    #[no_mangle]
+    static mut TRACE_BUFFER: TraceBuffer = TraceBuffer {
+        magic: 0,
+        tick_frequency: 0,
+        capacity: 0,
+        head: 0,
+        events: [TraceEvent {
+            timestamp: 0,
+            kind: 0,
+            task: 0,
+            hart: 0,
+            _pad: 0,
+            arg: 0,
+        }; TRACE_CAPACITY],
+    };
+
+    // For each hart, one more than the task last recorded as current, zero if
+    // there is none.
+    static mut CURRENT_TASK: [u8; arch::NUM_HARTS] = [0; arch::NUM_HARTS];
+
+    pub fn record(event: Event, task: u8, arg: u32) {
+        // Safety: Events are only recorded from within the kernel while
+        // TASK_TABLE_LOCK is held, so nothing else may be accessing
+        // TRACE_BUFFER.
+        let buffer = unsafe { &mut TRACE_BUFFER };
+
+        if buffer.magic != TRACE_MAGIC {
+            buffer.tick_frequency = time::tick_frequency();
+            buffer.capacity = TRACE_CAPACITY as u32;
+            buffer.magic = TRACE_MAGIC;
+        }
+
+        buffer.events[buffer.head as usize % TRACE_CAPACITY] = TraceEvent {
+            timestamp: time::now_ticks(),
+            kind: event as u8,
+            task,
+            hart: arch::hart_id() as u8,
+            _pad: 0,
+            arg,
+        };
+        buffer.head = buffer.head.wrapping_add(1);
+
+        // The kernel may resume any task after idling, so always record the
+        // next switch.
+        if let Event::Idle = event {
+            // Safety: As above.
+            unsafe { CURRENT_TASK[arch::hart_id()] = 0 };
+        }
+    }
+
+    pub fn record_switch(task: u8) {
+        // Safety: As above.
+        let current = unsafe { &mut CURRENT_TASK[arch::hart_id()] };
+
+        if *current != task + 1 {
+            *current = task + 1;
+            record(Event::Switch, task, 0);
+        }
+    }
+}
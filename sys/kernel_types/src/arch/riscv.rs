@@ -1,7 +1,87 @@
-const NUM_PMP_ENTRIES: usize = 4;
+use core::mem::size_of;
 
+use open_enum::open_enum;
+use zerocopy::{AsBytes, FromBytes, FromZeroes};
+
+// The number of PMP entries implemented by the device, selected with the
+// pmp_entries_N features. Without either feature the device has four entries.
+#[cfg(feature = "pmp_entries_16")]
+pub const NUM_PMP_ENTRIES: usize = 16;
+#[cfg(all(feature = "pmp_entries_8", not(feature = "pmp_entries_16")))]
+pub const NUM_PMP_ENTRIES: usize = 8;
+#[cfg(not(any(feature = "pmp_entries_8", feature = "pmp_entries_16")))]
+pub const NUM_PMP_ENTRIES: usize = 4;
+
+// Each pmpcfg register holds the configuration byte of XLEN / 8 entries.
+pub const NUM_PMP_CFG: usize = NUM_PMP_ENTRIES.div_ceil(size_of::<usize>());
+
+// The PMP registers are XLEN bits wide, pmp_cfg holds the configuration byte
+// of each entry in entry order as it is written to the pmpcfg registers.
 #[repr(C)]
 pub struct ArchTaskDescriptor {
-    pub pmp_addr: [u32; NUM_PMP_ENTRIES],
-    pub pmp_cfg: u32,
+    pub pmp_addr: [usize; NUM_PMP_ENTRIES],
+    pub pmp_cfg: [usize; NUM_PMP_CFG],
+}
+
+// An mcause value in the range designated for custom use, recorded when a task
+// is faulted for completing a cycle of SYS_CALLs. The mtval field holds a
+// bitmask of the tasks in the cycle.
+pub const MCAUSE_DEADLOCK: usize = 24;
+
+// An mcause value in the range designated for custom use, recorded when a task
+// is restarted for failing to check in before its watchdog period elapsed.
+pub const MCAUSE_WATCHDOG: usize = 25;
+
+// The machine state captured when a task takes an exception.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, AsBytes, FromBytes, FromZeroes)]
+pub struct FaultRecord {
+    pub mcause: usize,
+    pub mtval: usize,
+    pub pc: usize,
+    pub sp: usize,
+}
+
+// Written to CrashRecord::magic by the kernel, any other value means that the
+// record is not valid.
+pub const CRASH_RECORD_MAGIC: u32 = 0x48535243;
+
+pub const CRASH_MESSAGE_LENGTH: usize = 64;
+
+#[open_enum]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, AsBytes, FromBytes, FromZeroes)]
+pub enum CrashKind {
+    TaskFault,
+    KernelPanic,
+}
+
+// A record of the last crash, kept in RAM which is not initialized at boot so
+// that it can be read with SYS_READ_CRASH after a reset.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, AsBytes, FromBytes, FromZeroes)]
+pub struct CrashRecord {
+    pub magic: u32,
+    pub kind: CrashKind,
+    // The task that faulted, or u32::MAX if the kernel panicked before any
+    // task faulted. For a kernel panic this is the last task to fault since
+    // boot, which may be the cause of the panic.
+    pub task: u32,
+    pub _pad: [u8; size_of::<usize>() - 4],
+    pub fault: FaultRecord,
+    // The general purpose registers of the task, indexed by register number.
+    pub registers: [usize; 32],
+    // The panic message, truncated and padded with zeros.
+    pub message: [u8; CRASH_MESSAGE_LENGTH],
+}
+
+impl CrashRecord {
+    pub fn message(&self) -> &str {
+        let len = self
+            .message
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(CRASH_MESSAGE_LENGTH);
+        core::str::from_utf8(&self.message[..len]).unwrap_or("")
+    }
 }
@@ -56,3 +56,14 @@ macro_rules! task_id {
         unsafe { task_id }
     }};
 }
+
+#[macro_export]
+macro_rules! num_tasks {
+    () => {{
+        extern "C" {
+            #[link_name = "rtos.constant.num_tasks"]
+            static num_tasks: u8;
+        }
+        unsafe { num_tasks }
+    }};
+}
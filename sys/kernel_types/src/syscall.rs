@@ -8,6 +8,28 @@ pub mod abi {
     pub const SYS_NOTIFICATION_TIMER_BIT: usize = 31;
     pub const SYS_NOTIFICATION_TIMER: u32 = 1 << SYS_NOTIFICATION_TIMER_BIT;
 
+    // The maximum number of timer slots a task may have.
+    pub const MAX_TIMERS: usize = 4;
+
+    // The notification bit posted by a timer slot. Slot 0 posts
+    // SYS_NOTIFICATION_TIMER, the remaining slots use the bits immediately
+    // below SYS_NOTIFICATION_FAULT.
+    pub const fn sys_notification_timer_bit(slot: usize) -> usize {
+        match slot {
+            0 => SYS_NOTIFICATION_TIMER_BIT,
+            slot => SYS_NOTIFICATION_FAULT_BIT - slot,
+        }
+    }
+
+    pub const fn sys_notification_timer(slot: usize) -> u32 {
+        1 << sys_notification_timer_bit(slot)
+    }
+
+    // Posted to the supervisor task when any other task enters the fatal
+    // state.
+    pub const SYS_NOTIFICATION_FAULT_BIT: usize = 30;
+    pub const SYS_NOTIFICATION_FAULT: u32 = 1 << SYS_NOTIFICATION_FAULT_BIT;
+
     #[open_enum]
     #[repr(usize)]
     #[derive(Clone, Copy, AsBytes, FromBytes, FromZeroes)]
@@ -19,6 +41,93 @@ pub mod abi {
         Notify,
         SetTimer,
         InterruptControl,
+        RestartTask,
+        ReadFault,
+        CallTimeout,
+        BorrowInfo,
+        BorrowRead,
+        BorrowWrite,
+        Yield,
+        GetTime,
+        SetTimerAbsolute,
+        TaskInfo,
+        ReadCrash,
+        Heartbeat,
+        FaultedTasks,
+        TimerMissed,
+    }
+
+    // The state of a task as reported by SYS_TASK_INFO.
+    #[open_enum]
+    #[repr(u8)]
+    #[derive(Clone, Copy, Debug, AsBytes, FromBytes, FromZeroes)]
+    pub enum TaskState {
+        Fatal,
+        Ready,
+        CallRequest,
+        CallResponse,
+        Receive,
+    }
+
+    // The result of an IPC operation, returned alongside any message data.
+    #[open_enum]
+    #[repr(u8)]
+    #[derive(Clone, Copy, Debug, AsBytes, FromBytes, FromZeroes)]
+    pub enum IpcStatus {
+        Success,
+        // The deadline passed before a response was received.
+        Timeout,
+        // The target is not a valid task, or is the calling task.
+        InvalidTarget,
+        // The target is in the fatal state, or faulted before it responded.
+        TargetDead,
+        // The target is not waiting for a response from the calling task.
+        NotAwaitingReply,
+        // The lease does not exist, does not permit the access or does not
+        // describe memory owned by the lender.
+        InvalidLease,
+        // The application configuration does not permit the caller to make
+        // this request of the target.
+        AccessDenied,
+    }
+
+    #[open_enum]
+    #[repr(u8)]
+    #[derive(Clone, Copy, Debug, AsBytes, FromBytes, FromZeroes)]
+    pub enum LeaseAccess {
+        Read,
+        ReadWrite,
+    }
+
+    // A region of the caller's memory that the target of a SYS_CALL may copy
+    // to or from until it responds.
+    #[repr(C)]
+    #[derive(Clone, Copy, AsBytes, FromBytes, FromZeroes)]
+    pub struct Lease {
+        pub base: usize,
+        pub len: usize,
+        pub access: LeaseAccess,
+        pub _pad: [u8; ::core::mem::size_of::<usize>() - 1],
+    }
+
+    impl Lease {
+        pub fn read(buffer: &[u8]) -> Self {
+            Self {
+                base: buffer.as_ptr() as usize,
+                len: buffer.len(),
+                access: LeaseAccess::Read,
+                _pad: [0; ::core::mem::size_of::<usize>() - 1],
+            }
+        }
+
+        pub fn read_write(buffer: &mut [u8]) -> Self {
+            Self {
+                base: buffer.as_mut_ptr() as usize,
+                len: buffer.len(),
+                access: LeaseAccess::ReadWrite,
+                _pad: [0; ::core::mem::size_of::<usize>() - 1],
+            }
+        }
     }
 
     #[open_enum]
@@ -9,20 +9,89 @@ bitflags! {
         const BOOT = 0x01;
         // Task runs in a privileged mode (ie. machine mode on RISC-V).
         const PRIVILEGED = 0x02;
-        // If this task dies it is a critical error and should result in a
-        // kernel panic.
+        // If this task dies it is a critical error and should result in the
+        // configured CriticalFaultAction.
         const CRITICAL = 0x04;
+        // Task is notified when any other task faults and may restart tasks
+        // that are in the fatal state.
+        const SUPERVISOR = 0x08;
+        // Task may use SYS_TASK_INFO to read the status of any task.
+        const INTROSPECT = 0x10;
+        // Task may use the floating-point registers, which are saved and
+        // restored by the kernel. Any other task that uses them is faulted.
+        const FPU = 0x20;
     }
 }
 
+// The action taken by the kernel when a task with the CRITICAL flag enters the
+// fatal state.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CriticalFaultAction {
+    // Halt the system with a diagnostic.
+    Halt,
+    // Reset the system.
+    Reset,
+}
+
 #[repr(C)]
 pub struct TaskDescriptor {
     pub init_pc: LinkConst,
+    // The bounds of the task's stack, which grows down from stack_end.
+    pub stack_start: LinkConst,
+    pub stack_end: LinkConst,
+    // Lower is higher priority, priorities are compacted so that every
+    // priority is less than the number of tasks.
     pub priority: u8,
     pub flags: Flags,
+    // Bitmask of the tasks that this task may SYS_CALL, bit n is set if task
+    // id n is permitted.
+    pub uses: u32,
+    // Bitmask of the tasks that this task may SYS_NOTIFY.
+    pub notifies: u32,
+    // The time in microseconds the task may run for without checking in with
+    // SYS_HEARTBEAT, zero disables the watchdog.
+    pub watchdog_us: u32,
+    // The hart the task is pinned to, or ANY_HART if it may run on any hart.
+    pub hart: u8,
     pub arch: ArchTaskDescriptor,
 }
 
+pub const ANY_HART: u8 = u8::MAX;
+
+// The pattern written over a task's stack at start-up by
+// rtos_task_entry(paint_stack), the stack high-water mark is the lowest word
+// which no longer holds it. This must match rtos_macros.
+pub const STACK_PAINT: u32 = 0xa5a5a5a5;
+
+pub const MAX_TASK_NAME_LENGTH: usize = 16;
+
+// The name of a task, padded with zeros.
+#[repr(transparent)]
+pub struct TaskName([u8; MAX_TASK_NAME_LENGTH]);
+
+impl TaskName {
+    pub const fn new(name: &str) -> Self {
+        let bytes = name.as_bytes();
+        if bytes.len() > MAX_TASK_NAME_LENGTH {
+            panic!("Task name too long.");
+        }
+
+        let mut name = [0; MAX_TASK_NAME_LENGTH];
+        let mut i = 0;
+        while i < bytes.len() {
+            name[i] = bytes[i];
+            i += 1;
+        }
+
+        Self(name)
+    }
+
+    pub const fn bytes(&self) -> &[u8; MAX_TASK_NAME_LENGTH] {
+        &self.0
+    }
+}
+
 #[repr(transparent)]
 pub struct InterruptDescriptor(u32);
 
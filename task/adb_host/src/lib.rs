@@ -13,7 +13,7 @@ mod adb;
 #[panic_handler]
 #[cfg(target_os = "none")]
 fn panic(_info: &core::panic::PanicInfo) -> ! {
-    loop {}
+    syscall::sys_panic();
 }
 
 #[rtos_task_entry]
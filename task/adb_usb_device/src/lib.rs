@@ -42,12 +42,15 @@ fn panic(_info: &core::panic::PanicInfo) -> ! {
     use core::fmt::Write;
     let mut w = DebugWriter {};
     let _ = write!(w, "{}", _info);
-    loop {}
+    syscall::sys_panic();
 }
 
 static EP_MEM: ch32x035_usb::EndpointMemory<256> = ch32x035_usb::EndpointMemory::new();
 
-#[rtos_task_entry]
+const USB_TICK_TIMER: u8 = 0;
+const ADB_POLL_TIMER: u8 = 1;
+
+#[rtos_task_entry(paint_stack)]
 fn task_main() -> ! {
     let usart1 = unsafe { &*device::USART1::ptr() };
 
@@ -72,6 +75,20 @@ fn task_main() -> ! {
 
     uart_puts("test_task start\n");
 
+    // Report a crash from before the last reset.
+    if let Ok(Some(crash)) = syscall::sys_read_crash() {
+        use core::fmt::Write;
+        let mut w = DebugWriter {};
+        let _ = writeln!(
+            w,
+            "crash {:?}: task {}, {:x?}, {}",
+            crash.kind,
+            crash.task,
+            crash.fault,
+            crash.message()
+        );
+    }
+
     rcc_client
         .peripheral_clock_enable(Peripheral::UsbFs, 1)
         .unwrap();
@@ -118,10 +135,12 @@ fn task_main() -> ! {
     uart_puts("usb keyboard constructed\n");
 
     // Wait 500ms before attempting to interact with keyboard
-    syscall::sys_set_timer(false, 500 * 1000);
+    syscall::sys_set_timer(0, false, 500 * 1000);
     syscall::sys_receive::<0>();
 
-    let mut adb_host = rpc_adb_host::Client::new(task_id!("adb_host"));
+    // A single ADB transaction takes a few milliseconds, if adb_host has not
+    // replied well after that then give up rather than stall the USB stack.
+    let mut adb_host = rpc_adb_host::Client::with_timeout(task_id!("adb_host"), 20 * 1000);
 
     for addr in 1..16 {
         let TalkResult {
@@ -152,7 +171,9 @@ fn task_main() -> ! {
         let _ = writeln!(w, "len {}, data = [{:x}, {:x}, ..]", len, data[0], data[1],);
     }
 
-    syscall::sys_set_timer(true, 1000);
+    // Timer slot 0 drives the 1ms USB tick, slot 1 the 10ms ADB poll.
+    syscall::sys_set_timer(USB_TICK_TIMER, true, 1000);
+    syscall::sys_set_timer(ADB_POLL_TIMER, true, 10 * 1000);
 
     syscall::sys_interrupt_control(
         device::Interrupt::USART1 as usize,
@@ -167,8 +188,6 @@ fn task_main() -> ! {
         syscall::InterruptControl::Enable,
     );
 
-    let mut count = 0u32;
-
     // The NKRO report has 17 keys
     let mut down_keys = [Keyboard::NoEventIndicated; 17];
     let mut adb_leds = 0xffu8;
@@ -327,60 +346,79 @@ fn task_main() -> ! {
     }
 
     loop {
-        let syscall::ReceiveResult { notifications, .. } = syscall::sys_receive::<0>();
-
-        // Timer interrupt
-        if notifications & 0x80000000 != 0 {
-            if count == 0 {
-                // Update ADB keyboard leds
-                adb_host
-                    .listen(
-                        2,
-                        2,
-                        2,
-                        [0x00, adb_leds, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
-                    )
-                    .unwrap();
-
-                // Query ADB keyboard
-                let rpc_adb_host::TalkResult { len, data, .. } = adb_host.talk(2, 0).unwrap();
-
-                if (data[0] | data[1]) != 0 {
-                    update_down_keys(data[0], &mut down_keys);
-
-                    if data[1] != 0xff {
-                        update_down_keys(data[1], &mut down_keys);
-                    }
-                }
+        let syscall::ReceiveResult {
+            notifications,
+            missed_periods,
+            ..
+        } = syscall::sys_receive::<0>();
+
+        if notifications & syscall::abi::sys_notification_timer(ADB_POLL_TIMER as usize) != 0 {
+            // Report any polls that were skipped because the previous ones
+            // overran the poll period.
+            let missed_polls = syscall::sys_timer_missed(ADB_POLL_TIMER);
+            if missed_polls != 0 {
+                use core::fmt::Write;
+                let mut w = DebugWriter {};
+                let _ = writeln!(w, "adb poll overran, missed {} polls", missed_polls);
+            }
 
-                match keyboard.device().write_report(down_keys) {
-                    Err(usbd_human_interface_device::UsbHidError::WouldBlock) => {}
-                    Err(usbd_human_interface_device::UsbHidError::Duplicate) => {}
-                    Ok(_) => {}
-                    Err(e) => {
-                        core::panic!("Failed to write keyboard report: {:?}", e)
-                    }
-                };
-
-                let data = ((data[0] as u16) << 8) | (data[1] as u16);
-                if data != 0 {
-                    use core::fmt::Write;
-                    let mut w = DebugWriter {};
-                    let _ = writeln!(
-                        w,
-                        "adb_leds = {:x}, len {}, data = {:x}, keys = [{:?}, {:?}, {:?}, {:?}, ..]",
-                        adb_leds, len, data, down_keys[0], down_keys[1], down_keys[2], down_keys[3],
-                    );
+            // Update ADB keyboard leds, a failure here will be retried on
+            // the next poll.
+            let _ = adb_host.listen(
+                2,
+                2,
+                2,
+                [0x00, adb_leds, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            );
+
+            // Query ADB keyboard, treat a failed or timed out query as no
+            // key events so the USB side keeps running.
+            let rpc_adb_host::TalkResult { len, data, .. } =
+                adb_host.talk(2, 0).unwrap_or(TalkResult {
+                    service_request: 0,
+                    len: 0,
+                    data: [0; 8],
+                });
+
+            if (data[0] | data[1]) != 0 {
+                update_down_keys(data[0], &mut down_keys);
+
+                if data[1] != 0xff {
+                    update_down_keys(data[1], &mut down_keys);
                 }
             }
 
-            count = (count + 1) % 10;
-
-            match keyboard.tick() {
-                Ok(_) => {}
+            match keyboard.device().write_report(down_keys) {
                 Err(usbd_human_interface_device::UsbHidError::WouldBlock) => {}
+                Err(usbd_human_interface_device::UsbHidError::Duplicate) => {}
+                Ok(_) => {}
                 Err(e) => {
-                    core::panic!("Failed to process keyboard tick: {:?}", e)
+                    core::panic!("Failed to write keyboard report: {:?}", e)
+                }
+            };
+
+            let data = ((data[0] as u16) << 8) | (data[1] as u16);
+            if data != 0 {
+                use core::fmt::Write;
+                let mut w = DebugWriter {};
+                let _ = writeln!(
+                    w,
+                    "adb_leds = {:x}, len {}, data = {:x}, keys = [{:?}, {:?}, {:?}, {:?}, ..]",
+                    adb_leds, len, data, down_keys[0], down_keys[1], down_keys[2], down_keys[3],
+                );
+            }
+        }
+
+        if notifications & syscall::abi::sys_notification_timer(USB_TICK_TIMER as usize) != 0 {
+            // The keyboard expects a tick every 1ms, catch up on any that
+            // were missed.
+            for _ in 0..=missed_periods {
+                match keyboard.tick() {
+                    Ok(_) => {}
+                    Err(usbd_human_interface_device::UsbHidError::WouldBlock) => {}
+                    Err(e) => {
+                        core::panic!("Failed to process keyboard tick: {:?}", e)
+                    }
                 }
             }
         }
@@ -0,0 +1,28 @@
+#![feature(naked_functions)]
+#![no_std]
+
+use rtos_macros::rtos_task_entry;
+
+#[panic_handler]
+#[cfg(target_os = "none")]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    syscall::sys_panic();
+}
+
+#[rtos_task_entry]
+fn task_main() -> ! {
+    loop {
+        let syscall::ReceiveResult { notifications, .. } = syscall::sys_receive::<0>();
+
+        if notifications & syscall::abi::SYS_NOTIFICATION_FAULT != 0 {
+            // Restart only the tasks that have faulted, several may have
+            // faulted before we were able to run.
+            let mut faulted = syscall::sys_faulted_tasks();
+            while faulted != 0 {
+                let task = faulted.trailing_zeros();
+                faulted &= !(1 << task);
+                syscall::sys_restart_task(task as u8);
+            }
+        }
+    }
+}
@@ -3,8 +3,13 @@
 
 use core::mem;
 
+use kernel_types::task_id;
 use rtos_macros::rtos_task_entry;
 
+// The task's memory is covered by two PMP entries, see app.toml. This buffer is
+// placed in the data section so that it straddles the boundary between them.
+static mut BOUNDARY_BUFFER: [u8; 64] = [0; 64];
+
 #[panic_handler]
 #[cfg(target_os = "none")]
 fn panic(info: &core::panic::PanicInfo) -> ! {
@@ -12,7 +17,7 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
     loop {}
 }
 
-#[rtos_task_entry]
+#[rtos_task_entry(paint_stack)]
 fn task_main() -> ! {
     semihosting::println!("test_helper: start");
 
@@ -41,7 +46,7 @@ impl rpc::Notify for TestHelperServer {
 
 impl rpc_test_helper::TestHelper<rpc::CallStatus> for TestHelperServer {
     fn set_timer(&mut self, periodic: u8, deadline: u32) -> Result<(), rpc::CallStatus> {
-        syscall::sys_set_timer(periodic > 0, deadline);
+        syscall::sys_set_timer(0, periodic > 0, deadline);
         Ok(())
     }
 
@@ -59,6 +64,83 @@ impl rpc_test_helper::TestHelper<rpc::CallStatus> for TestHelperServer {
         self.buffer = buffer;
         Ok(old_buffer)
     }
+
+    fn sleep(&mut self, duration: u32) -> Result<(), rpc::CallStatus> {
+        syscall::sys_set_timer(0, false, duration);
+        let _: syscall::ReceiveResult<0> = syscall::sys_receive();
+        Ok(())
+    }
+
+    fn notify(&mut self, task_id: u8, notification: u32) -> Result<u8, rpc::CallStatus> {
+        Ok(syscall::sys_notify(task_id, notification).0)
+    }
+
+    fn spin(&mut self, heartbeat: u8, duration: u32) -> Result<(), rpc::CallStatus> {
+        let deadline = syscall::sys_get_time() + duration as u64;
+        while syscall::sys_get_time() < deadline {
+            if heartbeat > 0 {
+                syscall::sys_heartbeat();
+            }
+        }
+        Ok(())
+    }
+
+    fn fpu_swap(&mut self, value: u32) -> Result<u32, rpc::CallStatus> {
+        let old_value = rpc_test_helper::read_f0();
+        rpc_test_helper::write_f0(value);
+        Ok(old_value)
+    }
+
+    fn borrow_len(&mut self, lease: u8) -> Result<u32, rpc::CallStatus> {
+        let info = syscall::sys_borrow_info(task_id!("test_runner"), lease)
+            .map_err(|_| rpc::CallStatus::InvalidParameter)?;
+        Ok(info.len as u32)
+    }
+
+    fn borrow_read(&mut self, lease: u8, offset: u32) -> Result<u8, rpc::CallStatus> {
+        let result = syscall::sys_borrow_read(
+            task_id!("test_runner"),
+            lease,
+            offset as usize,
+            &mut self.buffer,
+        );
+        Ok(result.err().unwrap_or(syscall::abi::IpcStatus::Success).0)
+    }
+
+    fn borrow_write(&mut self, lease: u8, offset: u32) -> Result<u8, rpc::CallStatus> {
+        let result = syscall::sys_borrow_write(
+            task_id!("test_runner"),
+            lease,
+            offset as usize,
+            &self.buffer,
+        );
+        Ok(result.err().unwrap_or(syscall::abi::IpcStatus::Success).0)
+    }
+
+    fn borrow_read_boundary(&mut self, lease: u8) -> Result<u8, rpc::CallStatus> {
+        // Safety: The buffer is only referenced here, and the task has a
+        // single thread.
+        let buffer = unsafe { &mut *core::ptr::addr_of_mut!(BOUNDARY_BUFFER) };
+
+        // Read into the 16 bytes centred on the first 2048 byte aligned
+        // address within the buffer.
+        let boundary = (buffer.as_ptr() as usize | 2047) + 1 - buffer.as_ptr() as usize;
+        assert!((8..=buffer.len() - 8).contains(&boundary));
+        let window = &mut buffer[boundary - 8..boundary + 8];
+
+        let result = syscall::sys_borrow_read(task_id!("test_runner"), lease, 0, window);
+        self.buffer[..16].copy_from_slice(window);
+        Ok(result.err().unwrap_or(syscall::abi::IpcStatus::Success).0)
+    }
+
+    fn restart_task(&mut self, task_id: u8) -> Result<u8, rpc::CallStatus> {
+        Ok(syscall::sys_restart_task(task_id) as u8)
+    }
+
+    fn call(&mut self, task_id: u8) -> Result<u8, rpc::CallStatus> {
+        let result = syscall::sys_call::<0, 0>(task_id, 0, []);
+        Ok(result.status.0)
+    }
 }
 
 rpc::rpc_impl_dispatch_for!(TestHelperServer as rpc_test_helper::DispatchImpl);
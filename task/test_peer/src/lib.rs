@@ -0,0 +1,36 @@
+#![feature(naked_functions)]
+#![no_std]
+
+use rtos_macros::rtos_task_entry;
+
+#[panic_handler]
+#[cfg(target_os = "none")]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    semihosting::eprintln!("test_peer: {}", info);
+    loop {}
+}
+
+// test_peer has the same priority as test_runner, so it only runs when
+// test_runner gives way to it. Each notification starts a different test.
+const NOTIFICATION_YIELD: u32 = 1 << 0;
+const NOTIFICATION_SPIN: u32 = 1 << 1;
+
+#[rtos_task_entry]
+fn task_main() -> ! {
+    semihosting::println!("test_peer: start");
+
+    loop {
+        let syscall::ReceiveResult { notifications, .. } = syscall::sys_receive::<0>();
+
+        if notifications & NOTIFICATION_YIELD != 0 {
+            // Give way once before blocking again.
+            syscall::sys_yield();
+        }
+
+        if notifications & NOTIFICATION_SPIN != 0 {
+            // Run for longer than a time slice without giving way.
+            let deadline = syscall::sys_get_time() + 10_000;
+            while syscall::sys_get_time() < deadline {}
+        }
+    }
+}
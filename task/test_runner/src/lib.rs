@@ -33,7 +33,7 @@ fn task_main() -> ! {
     {
         client.set_timer(0, 20_000).unwrap();
 
-        syscall::sys_set_timer(false, 10_000);
+        syscall::sys_set_timer(0, false, 10_000);
         let _: syscall::ReceiveResult<0> = syscall::sys_receive();
 
         let expiration_count = client
@@ -41,7 +41,7 @@ fn task_main() -> ! {
             .unwrap();
         assert_eq!(0, expiration_count);
 
-        syscall::sys_set_timer(false, 10_000);
+        syscall::sys_set_timer(0, false, 10_000);
         let _: syscall::ReceiveResult<0> = syscall::sys_receive();
 
         let expiration_count = client
@@ -49,7 +49,7 @@ fn task_main() -> ! {
             .unwrap();
         assert_eq!(1, expiration_count);
 
-        syscall::sys_set_timer(false, 20_000);
+        syscall::sys_set_timer(0, false, 20_000);
         let _: syscall::ReceiveResult<0> = syscall::sys_receive();
 
         let expiration_count = client
@@ -61,7 +61,7 @@ fn task_main() -> ! {
     {
         client.set_timer(1, 10_000).unwrap();
 
-        syscall::sys_set_timer(false, 55_000);
+        syscall::sys_set_timer(0, false, 55_000);
         let _: syscall::ReceiveResult<0> = syscall::sys_receive();
 
         let expiration_count = client
@@ -71,7 +71,7 @@ fn task_main() -> ! {
 
         client.set_timer(0, 0).unwrap();
 
-        syscall::sys_set_timer(false, 10_000);
+        syscall::sys_set_timer(0, false, 10_000);
         let _: syscall::ReceiveResult<0> = syscall::sys_receive();
 
         let expiration_count = client
@@ -99,7 +99,7 @@ fn task_main() -> ! {
 
         syscall::sys_notify(task_id!("test_helper"), 1);
 
-        syscall::sys_set_timer(false, 10_000);
+        syscall::sys_set_timer(0, false, 10_000);
         let _: syscall::ReceiveResult<0> = syscall::sys_receive();
 
         let notification_count = client.notification_count(0).unwrap();
@@ -127,6 +127,270 @@ fn task_main() -> ! {
         }
     }
 
+    {
+        // test_helper may copy to and from memory that test_runner lends it
+        // for the duration of a call.
+        let input: [u8; 16] = core::array::from_fn(|i| (i + 1) as u8);
+        let mut output = [0u8; 16];
+        let leases = [
+            syscall::abi::Lease::read(&input),
+            syscall::abi::Lease::read_write(&mut output),
+            // test_runner can't lend memory that it doesn't own.
+            syscall::abi::Lease {
+                base: 0,
+                len: 16,
+                access: syscall::abi::LeaseAccess::Read,
+                _pad: [0; core::mem::size_of::<usize>() - 1],
+            },
+        ];
+        let mut lease_client =
+            rpc_test_helper::Client::with_leases(task_id!("test_helper"), &leases);
+        let success = syscall::abi::IpcStatus::Success.0;
+        let invalid_lease = syscall::abi::IpcStatus::InvalidLease.0;
+
+        assert_eq!(16, lease_client.borrow_len(0).unwrap());
+        let err = lease_client.borrow_len(3).unwrap_err();
+        assert_eq!(rpc_test_helper::CallStatus::InvalidParameter, err);
+
+        // A read stops at the end of the lease.
+        assert_eq!(success, lease_client.borrow_read(0, 8).unwrap());
+        let buffer = client
+            .swap_buffer(core::array::from_fn(|i| 0x80 + i as u8))
+            .unwrap();
+        assert_eq!(input[8..], buffer[..8]);
+        assert!(buffer[8..].iter().all(|reg| *reg == 0));
+
+        // As does a write.
+        assert_eq!(success, lease_client.borrow_write(1, 4).unwrap());
+        assert!(output[..4].iter().all(|reg| *reg == 0));
+        for (i, reg) in output[4..].iter().enumerate() {
+            assert_eq!(0x80 + i as u8, *reg);
+        }
+
+        // A read only lease can't be written.
+        assert_eq!(invalid_lease, lease_client.borrow_write(0, 0).unwrap());
+        for (i, reg) in input.iter().enumerate() {
+            assert_eq!((i + 1) as u8, *reg);
+        }
+
+        // Accesses outside of a lease, or of memory the lender doesn't own,
+        // are refused.
+        assert_eq!(invalid_lease, lease_client.borrow_read(0, 17).unwrap());
+        assert_eq!(invalid_lease, lease_client.borrow_read(2, 0).unwrap());
+        assert_eq!(invalid_lease, lease_client.borrow_read(3, 0).unwrap());
+
+        let buffer = client.swap_buffer([0; 36]).unwrap();
+        for (i, reg) in buffer.iter().enumerate() {
+            assert_eq!(0x80 + i as u8, *reg);
+        }
+
+        // A buffer which spans two PMP entries is accessible.
+        assert_eq!(success, lease_client.borrow_read_boundary(0).unwrap());
+        let buffer = client.swap_buffer([0; 36]).unwrap();
+        assert_eq!(input, buffer[..16]);
+    }
+
+    {
+        let start = syscall::sys_get_time();
+
+        syscall::sys_set_timer(0, false, 10_000);
+        let _: syscall::ReceiveResult<0> = syscall::sys_receive();
+
+        let now = syscall::sys_get_time();
+        assert!(now >= start + 10_000);
+
+        syscall::sys_set_timer_absolute(0, now + 20_000);
+        let _: syscall::ReceiveResult<0> = syscall::sys_receive();
+
+        assert!(syscall::sys_get_time() >= now + 20_000);
+    }
+
+    {
+        // A long timer still fires while every task is blocked and the kernel
+        // is sleeping, it is the only thing that can wake the system.
+        let start = syscall::sys_get_time();
+
+        syscall::sys_set_timer(0, false, 250_000);
+        let result: syscall::ReceiveResult<0> = syscall::sys_receive();
+        assert_eq!(syscall::abi::SYS_NOTIFICATION_TIMER, result.notifications);
+
+        assert!(syscall::sys_get_time() >= start + 250_000);
+    }
+
+    {
+        // Each timer slot posts its own notification.
+        let slot_1 = syscall::abi::sys_notification_timer(1);
+
+        syscall::sys_set_timer(0, false, 20_000);
+        syscall::sys_set_timer(1, false, 10_000);
+
+        let result: syscall::ReceiveResult<0> = syscall::sys_receive();
+        assert_eq!(slot_1, result.notifications);
+
+        let result: syscall::ReceiveResult<0> = syscall::sys_receive();
+        assert_eq!(syscall::abi::SYS_NOTIFICATION_TIMER, result.notifications);
+    }
+
+    {
+        // Block in a call for several periods, the expirations that could not
+        // be received are reported as missed.
+        syscall::sys_set_timer(0, true, 10_000);
+        client.sleep(35_000).unwrap();
+
+        let result: syscall::ReceiveResult<0> = syscall::sys_receive();
+        let missed_again = syscall::sys_timer_missed(0);
+        syscall::sys_set_timer(0, false, 0);
+
+        assert!(result.notifications & syscall::abi::SYS_NOTIFICATION_TIMER != 0);
+        assert!(result.missed_periods >= 2);
+        assert_eq!(0, missed_again);
+
+        // The missed expirations of other slots are read separately.
+        let slot_1 = syscall::abi::sys_notification_timer(1);
+        syscall::sys_set_timer(1, true, 10_000);
+        client.sleep(35_000).unwrap();
+
+        let result: syscall::ReceiveResult<0> = syscall::sys_receive();
+        let missed_periods = syscall::sys_timer_missed(1);
+        let missed_again = syscall::sys_timer_missed(1);
+        syscall::sys_set_timer(1, false, 0);
+
+        assert!(result.notifications & slot_1 != 0);
+        assert_eq!(0, result.missed_periods);
+        assert!(missed_periods >= 2);
+        assert_eq!(0, missed_again);
+    }
+
+    {
+        let status = syscall::sys_notify(task_id!("test_runner"), 1);
+        assert_eq!(syscall::abi::IpcStatus::InvalidTarget, status);
+
+        let status = syscall::sys_notify(u8::MAX, 1);
+        assert_eq!(syscall::abi::IpcStatus::InvalidTarget, status);
+
+        // test_helper is not permitted to notify test_runner.
+        let status = client.notify(task_id!("test_runner"), 1).unwrap();
+        assert_eq!(syscall::abi::IpcStatus::AccessDenied.0, status);
+
+        let status = syscall::sys_send(task_id!("test_helper"), 0, []);
+        assert_eq!(syscall::abi::IpcStatus::NotAwaitingReply, status);
+
+        let mut invalid_client = rpc_test_helper::Client::new(u8::MAX);
+        let err = invalid_client.notification_count(0).unwrap_err();
+        assert_eq!(rpc_test_helper::CallStatus::InvalidTarget, err);
+    }
+
+    {
+        // test_helper is waiting to receive while test_runner runs.
+        let info = syscall::sys_task_info(task_id!("test_helper")).unwrap();
+        assert_eq!("test_helper", info.name());
+        assert_eq!(syscall::abi::TaskState::Receive, info.state);
+        // Priorities are reported by rank, test_helper has the second highest.
+        assert_eq!(1, info.priority);
+        assert_eq!(None, info.blocked_on);
+
+        // test_helper paints its stack, and has used some but not all of it.
+        assert!(info.stack_used > 0);
+        assert!(info.stack_used < info.stack_size);
+
+        let info = syscall::sys_task_info(task_id!("test_runner")).unwrap();
+        assert_eq!("test_runner", info.name());
+        assert_eq!(syscall::abi::TaskState::Ready, info.state);
+
+        let err = syscall::sys_task_info(u8::MAX).err();
+        assert_eq!(Some(syscall::abi::IpcStatus::InvalidTarget), err);
+    }
+
+    {
+        // test_peer has the same priority as test_runner, once notified it is
+        // ready but only runs when test_runner gives way.
+        let peer = task_id!("test_peer");
+        syscall::sys_notify(peer, 1 << 0);
+        let info = syscall::sys_task_info(peer).unwrap();
+        assert_eq!(syscall::abi::TaskState::Ready, info.state);
+
+        // test_peer yields straight back, then blocks when it next runs.
+        syscall::sys_yield();
+        let info = syscall::sys_task_info(peer).unwrap();
+        assert_eq!(syscall::abi::TaskState::Ready, info.state);
+
+        syscall::sys_yield();
+        let info = syscall::sys_task_info(peer).unwrap();
+        assert_eq!(syscall::abi::TaskState::Receive, info.state);
+    }
+
+    {
+        // test_peer spins without giving way, test_runner never gives way
+        // either but their time slices alternate until test_peer blocks.
+        let peer = task_id!("test_peer");
+        syscall::sys_notify(peer, 1 << 1);
+
+        let deadline = syscall::sys_get_time() + 100_000;
+        while syscall::sys_task_info(peer).unwrap().state != syscall::abi::TaskState::Receive {
+            assert!(syscall::sys_get_time() < deadline);
+        }
+    }
+
+    {
+        // Any crash record from before this boot is cleared once read.
+        let _ = syscall::sys_read_crash().unwrap();
+        assert!(syscall::sys_read_crash().unwrap().is_none());
+    }
+
+    {
+        // Each task has its own FP registers, test_helper's are restored from
+        // zero on its first use.
+        rpc_test_helper::write_f0(0x1234_5678);
+
+        let old_value = client.fpu_swap(0xabcd_ef01).unwrap();
+        assert_eq!(0, old_value);
+        assert_eq!(0x1234_5678, rpc_test_helper::read_f0());
+
+        let old_value = client.fpu_swap(0).unwrap();
+        assert_eq!(0xabcd_ef01, old_value);
+        assert_eq!(0x1234_5678, rpc_test_helper::read_f0());
+    }
+
+    {
+        // test_runner claims three peripherals, the adjacent test and rtc
+        // windows share a PMP entry.
+        const RTC_TIME_LOW: *const u32 = 0x0010_1000 as *const u32;
+        const RTC_TIME_HIGH: *const u32 = 0x0010_1004 as *const u32;
+        const UART0_LSR: *const u8 = 0x1000_0005 as *const u8;
+
+        // Safety: These registers may be read at any time. Reading the low
+        // word of the time latches the high word, and reading the line status
+        // register only clears error flags that are unused here.
+        let (time_high, lsr) = unsafe {
+            RTC_TIME_LOW.read_volatile();
+            (RTC_TIME_HIGH.read_volatile(), UART0_LSR.read_volatile())
+        };
+
+        // The RTC holds nanoseconds since the epoch.
+        assert_ne!(0, time_high);
+        // The UART transmit holding register is empty.
+        assert_ne!(0, lsr & 0x20);
+    }
+
+    {
+        let mut timeout_client =
+            rpc_test_helper::Client::with_timeout(task_id!("test_helper"), 10_000);
+
+        timeout_client.sleep(5_000).unwrap();
+
+        let err = timeout_client.sleep(50_000).unwrap_err();
+        assert_eq!(rpc_test_helper::CallStatus::Timeout, err);
+
+        // Let the helper finish sleeping, its late reply is discarded.
+        syscall::sys_set_timer(0, false, 60_000);
+        let _: syscall::ReceiveResult<0> = syscall::sys_receive();
+
+        let buffer = timeout_client.swap_buffer([0; 36]).unwrap();
+        for reg in buffer {
+            assert_eq!(0, reg);
+        }
+    }
+
     {
         for i in 0..32 {
             let expiration_count = client.notification_count(i).unwrap();
@@ -134,6 +398,87 @@ fn task_main() -> ! {
         }
     }
 
+    {
+        // test_helper is not the supervisor, attempting to restart a task
+        // faults it and fails the call that was in flight.
+        client.swap_buffer([1; 36]).unwrap();
+        client.fpu_swap(0xabcd_ef01).unwrap();
+        let err = client.restart_task(task_id!("test_runner")).unwrap_err();
+        assert_eq!(rpc_test_helper::CallStatus::TargetDead, err);
+
+        // test_runner is the supervisor, it is notified of the fault and can
+        // see which task faulted.
+        let result: syscall::ReceiveResult<0> = syscall::sys_receive();
+        assert_eq!(syscall::abi::SYS_NOTIFICATION_FAULT, result.notifications);
+        assert_eq!(1 << task_id!("test_helper"), syscall::sys_faulted_tasks());
+
+        // Only a faulted task is restarted.
+        assert!(syscall::sys_restart_task(task_id!("test_helper")));
+        assert_eq!(0, syscall::sys_faulted_tasks());
+        assert!(!syscall::sys_restart_task(task_id!("test_helper")));
+
+        // The restarted task serves requests again, from its initial state,
+        // including its FP registers.
+        let buffer = client.swap_buffer([0; 36]).unwrap();
+        for reg in buffer {
+            assert_eq!(0, reg);
+        }
+        assert_eq!(0, client.fpu_swap(0).unwrap());
+    }
+
+    {
+        // test_helper calling test_runner while test_runner is blocked on it
+        // would leave both blocked forever, so test_helper is faulted.
+        let err = client.call(task_id!("test_runner")).unwrap_err();
+        assert_eq!(rpc_test_helper::CallStatus::TargetDead, err);
+
+        let result: syscall::ReceiveResult<0> = syscall::sys_receive();
+        assert_eq!(syscall::abi::SYS_NOTIFICATION_FAULT, result.notifications);
+
+        // The fault record holds the tasks in the cycle.
+        let record = syscall::sys_read_fault(task_id!("test_helper")).unwrap();
+        assert_eq!(kernel_types::arch::MCAUSE_DEADLOCK, record.mcause);
+        let cycle: usize = (1 << task_id!("test_runner")) | (1 << task_id!("test_helper"));
+        assert_eq!(cycle, record.mtval);
+
+        assert!(syscall::sys_restart_task(task_id!("test_helper")));
+    }
+
+    {
+        // test_helper is ready but starved for longer than its watchdog
+        // period while test_runner spins, the period only counts down while
+        // test_helper runs.
+        syscall::sys_notify(task_id!("test_helper"), 1);
+        let deadline = syscall::sys_get_time() + 50_000;
+        while syscall::sys_get_time() < deadline {}
+
+        assert_eq!(1, client.notification_count(0).unwrap());
+        assert!(syscall::sys_read_fault(task_id!("test_helper")).is_none());
+    }
+
+    {
+        // test_helper survives spinning for longer than its watchdog period
+        // while it checks in.
+        client.spin(1, 50_000).unwrap();
+
+        // Without checking in it is restarted, losing the call in flight.
+        client.swap_buffer([1; 36]).unwrap();
+        client.fpu_swap(0xabcd_ef01).unwrap();
+        let err = client.spin(0, 50_000).unwrap_err();
+        assert_eq!(rpc_test_helper::CallStatus::TargetDead, err);
+
+        // The restart is not reported as a fault, but the record is kept.
+        assert_eq!(0, syscall::sys_faulted_tasks());
+        let record = syscall::sys_read_fault(task_id!("test_helper")).unwrap();
+        assert_eq!(kernel_types::arch::MCAUSE_WATCHDOG, record.mcause);
+
+        let buffer = client.swap_buffer([0; 36]).unwrap();
+        for reg in buffer {
+            assert_eq!(0, reg);
+        }
+        assert_eq!(0, client.fpu_swap(0).unwrap());
+    }
+
     semihosting::println!("test_runner: finish");
     panic!();
 }
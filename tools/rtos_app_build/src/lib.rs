@@ -24,6 +24,15 @@ struct Peripheral {
 #[derive(Debug, Serialize, Deserialize)]
 struct DeviceConfig {
     family: String,
+    // The register widths that the device can be built for.
+    #[serde(default = "default_xlen")]
+    xlen: Vec<u32>,
+    // Whether the device has the F and D extensions.
+    #[serde(default)]
+    fpu: bool,
+    // The number of PMP entries the device implements, the kernel must be
+    // built with the matching pmp_entries_N feature.
+    pmp_entries: u32,
     flash: MemoryRange,
     ram: MemoryRange,
     peripherals: BTreeMap<String, Peripheral>,
@@ -48,9 +57,49 @@ struct TargetConfig {
     clock: u32,
 }
 
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CriticalFaultAction {
+    #[default]
+    Halt,
+    Reset,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct KernelConfig {
     memory: MemoryConfig,
+    #[serde(default)]
+    critical_fault: CriticalFaultAction,
+    // The time slice in microseconds given to each of a set of ready tasks
+    // with equal priority, zero disables time slicing.
+    #[serde(default)]
+    time_slice: u32,
+    // The number of timer slots each task has, the kernel must be built with
+    // the matching num_timers_N feature.
+    #[serde(default = "default_timers")]
+    timers: u32,
+    // The number of harts that run tasks, the kernel must be built with the
+    // matching num_harts_N feature. The kernel stack is divided equally
+    // between the harts.
+    #[serde(default = "default_harts")]
+    harts: u32,
+    // On WCH parts, enter deep sleep when idle with no deadline pending. Deep
+    // sleep stops SysTick, so it is never entered while a timer, call timeout
+    // or watchdog is running.
+    #[serde(default)]
+    deep_sleep: bool,
+}
+
+fn default_timers() -> u32 {
+    1
+}
+
+fn default_harts() -> u32 {
+    1
+}
+
+fn default_xlen() -> Vec<u32> {
+    vec![32]
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,9 +107,34 @@ struct TaskConfig {
     priority: u8,
     #[serde(default)]
     boot: bool,
+    #[serde(default)]
+    supervisor: bool,
+    #[serde(default)]
+    critical: bool,
+    // The task may read the status of any task with SYS_TASK_INFO.
+    #[serde(default)]
+    introspect: bool,
+    // The time in microseconds the task may run for without checking in with
+    // SYS_HEARTBEAT before it is restarted, or the system is reset if the
+    // task is critical. Zero disables the watchdog.
+    #[serde(default)]
+    watchdog: u32,
+    // Pin the task to a hart, by default it may run on any hart.
+    #[serde(default)]
+    hart: Option<u8>,
+    // The task may use the floating-point registers, the kernel must be built
+    // with the fpu feature.
+    #[serde(default)]
+    fpu: bool,
     memory: MemoryConfig,
     #[serde(default)]
     peripherals: Vec<String>,
+    // Tasks that this task may SYS_CALL.
+    #[serde(default)]
+    uses: Vec<String>,
+    // Tasks that this task may SYS_NOTIFY.
+    #[serde(default)]
+    notifies: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -80,7 +154,18 @@ struct Task {
     name: String,
     priority: u8,
     boot: bool,
+    supervisor: bool,
+    critical: bool,
+    introspect: bool,
+    watchdog: u32,
+    hart: Option<u8>,
+    fpu: bool,
+    uses: Vec<String>,
+    notifies: Vec<String>,
     base_address: Option<u32>,
+    // The task memory is a TOR region, which may be any multiple of sixteen
+    // bytes and need not be aligned to its size. Otherwise it is a NAPOT region.
+    tor_region: bool,
     memory_config: MemoryConfig,
     memory_regions: Vec<MemoryRegion>,
     interrupts: Vec<Interrupt>,
@@ -92,8 +177,15 @@ struct AppConfig {
     tasks: Vec<Task>,
     device: DeviceConfig,
     feature_assertions: Vec<String>,
+    noinit_base: u32,
+    noinit_size: u32,
 }
 
+// RAM reserved for data that is not initialized at boot, this holds the
+// kernel crash record. This is the size for RV32, the record holds the task's
+// registers and so is scaled by XLEN.
+const NOINIT_SIZE: u32 = 256;
+
 pub fn build() {
     let out_dir: std::path::PathBuf = std::env::var_os("OUT_DIR").unwrap().into();
     let manifest_dir: std::path::PathBuf = std::env::var_os("CARGO_MANIFEST_DIR").unwrap().into();
@@ -120,12 +212,113 @@ pub fn build() {
     let device_config_file = std::fs::read_to_string(device_config_path).unwrap();
     let device_config: DeviceConfig = toml::from_str(&device_config_file).unwrap();
 
+    // The app is built for the same target as the kernel and tasks.
+    let xlen: u32 = std::env::var("CARGO_CFG_TARGET_POINTER_WIDTH")
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    if !device_config.xlen.contains(&xlen) {
+        panic!(
+            "The {} device does not support RV{xlen}",
+            config.target.device
+        );
+    }
+
+    // Must match NUM_PMP_ENTRIES in kernel_types.
+    if ![4, 8, 16].contains(&device_config.pmp_entries) {
+        panic!("The number of PMP entries must be 4, 8 or 16");
+    }
+
     let total_kernel_memory = config.kernel.memory.stack + config.kernel.memory.data;
 
     if (total_kernel_memory & total_kernel_memory.wrapping_sub(1)) != 0 {
         panic!("Total kernel memory (stack + data) must be a power of two");
     }
 
+    // Must match MAX_TIMERS in kernel_types.
+    if !(1..=4).contains(&config.kernel.timers) {
+        panic!("The number of timers must be between 1 and 4");
+    }
+
+    if !(1..=4).contains(&config.kernel.harts) {
+        panic!("The number of harts must be between 1 and 4");
+    }
+
+    // Only the generic family has the ACLINT needed to start other harts.
+    if config.kernel.harts > 1 && device_config.family != "generic" {
+        panic!(
+            "Multiple harts are not supported by the {} family",
+            device_config.family
+        );
+    }
+
+    // Interrupt notifications are allocated upwards from bit 0, and timer
+    // slots other than the first are allocated downwards from below the fault
+    // notification.
+    let max_interrupts = 31 - config.kernel.timers as usize;
+
+    let supervisor_count = config.tasks.values().filter(|t| t.supervisor).count();
+    if supervisor_count > 1 {
+        panic!("Only one task may be the supervisor, found {supervisor_count}");
+    }
+
+    #[repr(u8)]
+    #[derive(Debug, Clone, Copy)]
+    #[allow(unused)]
+    enum PmpMode {
+        Off = 0,
+        TopOfRange = 1,
+        NaturallyAlignedFourByte = 2,
+        NaturallyAlignedPowerTwo = 3,
+    }
+
+    // Cover base..base + size with the fewest PMP entries. The range is split
+    // into naturally aligned power of two blocks, each taking one entry, unless
+    // that needs more than the two entries of a TOR region: one to hold the
+    // base address followed by the TOR entry itself. The PMP registers are XLEN
+    // bits wide, the addresses are emitted as usize for the target.
+    let pmp_range = |base: u32, size: u32| {
+        if (base | size) & 3 != 0 {
+            panic!("PMP region {base:#x} with size {size:#x} is not a multiple of four bytes");
+        }
+
+        let top = base as u64 + size as u64;
+        let mut blocks = Vec::new();
+        let mut block_base = base as u64;
+        while block_base < top {
+            let align = 1u64
+                .checked_shl(block_base.trailing_zeros())
+                .unwrap_or(u64::MAX);
+            let block_size = align.min(1 << (top - block_base).ilog2());
+
+            blocks.push(match block_size {
+                4 => (PmpMode::NaturallyAlignedFourByte, block_base as usize >> 2),
+                _ => (
+                    PmpMode::NaturallyAlignedPowerTwo,
+                    (block_base as usize >> 2) | ((block_size as usize >> 3) - 1),
+                ),
+            });
+            block_base += block_size;
+        }
+
+        if blocks.len() <= 2 {
+            blocks
+        } else {
+            vec![
+                (PmpMode::Off, base as usize >> 2),
+                (PmpMode::TopOfRange, top as usize >> 2),
+            ]
+        }
+    };
+
+    let pmp_cfg = |l: bool, a: PmpMode, x: bool, w: bool, r: bool| {
+        ((r as u8) << 0) | ((w as u8) << 1) | ((x as u8) << 2) | ((a as u8) << 3) | ((l as u8) << 7)
+    };
+
+    let pmp_entries = device_config.pmp_entries as usize;
+    let flash_pmp_entries = pmp_range(device_config.flash.base, device_config.flash.size).len();
+
     let mut claimed_peripherals = HashSet::new();
     let mut tasks: Vec<Task> = config
         .tasks
@@ -134,11 +327,25 @@ pub fn build() {
             let mut memory_regions = Vec::new();
             let mut interrupts = Vec::new();
 
-            let total_memory = task_config.memory.stack + task_config.memory.data;
-            if (total_memory & total_memory.wrapping_sub(1)) != 0 {
-                panic!(
-                    "Total memory assigned to '{task_name}' (stack + data) must be a power of two"
-                );
+            // The stack is at the base of the task memory, so this keeps the
+            // initial stack pointer aligned as the psABI requires.
+            if (task_config.memory.stack | task_config.memory.data) & 15 != 0 {
+                panic!("Stack and data assigned to '{task_name}' must be multiples of 16 bytes");
+            }
+
+            // Must match MAX_TASK_NAME_LENGTH in kernel_types.
+            if task_name.len() > 16 {
+                panic!("Task name '{task_name}' is longer than 16 bytes");
+            }
+
+            for target_name in task_config.uses.iter().chain(&task_config.notifies) {
+                if !config.tasks.contains_key(target_name) {
+                    panic!("Task '{task_name}' refers to unknown task '{target_name}'");
+                }
+
+                if target_name == task_name {
+                    panic!("Task '{task_name}' may not use or notify itself");
+                }
             }
 
             for peripheral_name in &task_config.peripherals {
@@ -161,13 +368,76 @@ pub fn build() {
                 }
             }
 
+            // Peripherals with adjacent memory are merged, so that they may
+            // share PMP entries.
+            memory_regions.sort_unstable_by_key(|r| r.base);
+            let mut merged_regions: Vec<MemoryRegion> = Vec::new();
+            for region in memory_regions {
+                match merged_regions.last_mut() {
+                    Some(last) if last.base + last.size == region.base => {
+                        last.size += region.size;
+                    }
+                    _ => merged_regions.push(region),
+                }
+            }
+
+            // The task memory takes the PMP entries left after flash and the
+            // peripherals. With two or more it may be a TOR region of any size,
+            // otherwise it must be a single NAPOT region.
+            let used_pmp_entries = flash_pmp_entries
+                + merged_regions
+                    .iter()
+                    .map(|r| pmp_range(r.base, r.size).len())
+                    .sum::<usize>();
+            if used_pmp_entries >= pmp_entries {
+                panic!(
+                    "Task '{task_name}' needs more PMP entries for its peripherals than the {} device has",
+                    config.target.device
+                );
+            }
+
+            let tor_region = pmp_entries - used_pmp_entries >= 2;
+            let total_memory = task_config.memory.stack + task_config.memory.data;
+            if !tor_region && (total_memory & total_memory.wrapping_sub(1)) != 0 {
+                panic!(
+                    "Total memory assigned to '{task_name}' (stack + data) must be a power of two, \
+                     as it has only one PMP entry left after its peripherals"
+                );
+            }
+
+            if interrupts.len() > max_interrupts {
+                panic!("Task '{task_name}' has more interrupts than notification bits available");
+            }
+
+            if let Some(hart) = task_config.hart {
+                if hart as u32 >= config.kernel.harts {
+                    panic!("Task '{task_name}' is pinned to hart {hart} which does not exist");
+                }
+            }
+
+            if task_config.fpu && !device_config.fpu {
+                panic!(
+                    "Task '{task_name}' uses the FPU but the {} device does not have one",
+                    config.target.device
+                );
+            }
+
             Task {
                 name: task_name.clone(),
                 priority: task_config.priority,
                 boot: task_config.boot,
+                supervisor: task_config.supervisor,
+                critical: task_config.critical,
+                introspect: task_config.introspect,
+                watchdog: task_config.watchdog,
+                hart: task_config.hart,
+                fpu: task_config.fpu,
+                uses: task_config.uses.clone(),
+                notifies: task_config.notifies.clone(),
                 base_address: None,
+                tor_region,
                 memory_config: task_config.memory,
-                memory_regions,
+                memory_regions: merged_regions,
                 interrupts,
             }
         })
@@ -187,14 +457,18 @@ pub fn build() {
     let _kernel_data_base = base_address;
     base_address += config.kernel.memory.data;
 
-    let mut allocations_remaining = tasks.len();
+    // Tasks with NAPOT regions are allocated first, each aligned to its size.
+    // The gaps left by aligning are then filled by tasks with TOR regions.
+    let mut gaps: Vec<(u32, u32)> = Vec::new();
+
+    let mut allocations_remaining = tasks.iter().filter(|t| !t.tor_region).count();
     'outer: while allocations_remaining > 0 {
         let npot = 2_u32.pow(base_address.trailing_zeros());
 
         // First try to find the task with the largest memory allocation that
         // is at most npot.
         for task in tasks.iter_mut() {
-            if task.base_address.is_some() {
+            if task.base_address.is_some() || task.tor_region {
                 continue;
             }
 
@@ -209,12 +483,14 @@ pub fn build() {
         // Otherwise find the task with the smallest memory allocation larger
         // than npot, and align up the base address.
         for task in tasks.iter_mut().rev() {
-            if task.base_address.is_some() {
+            if task.base_address.is_some() || task.tor_region {
                 continue;
             }
 
             let npot = task.memory_config.data + task.memory_config.stack;
-            base_address = (base_address + npot - 1) & !(npot - 1);
+            let aligned_base_address = (base_address + npot - 1) & !(npot - 1);
+            gaps.push((base_address, aligned_base_address - base_address));
+            base_address = aligned_base_address;
             task.base_address = Some(base_address);
             base_address += npot;
             allocations_remaining -= 1;
@@ -222,7 +498,36 @@ pub fn build() {
         }
     }
 
-    let memory_used = base_address - device_config.ram.base;
+    // Tasks are still sorted from largest to smallest, each TOR region is
+    // placed in the first gap it fits, or after all other tasks. The base of
+    // each is aligned to 16 bytes, as is the top of its stack.
+    let align_16 = |address: u32| (address + 15) & !15;
+    for task in tasks.iter_mut().filter(|t| t.tor_region) {
+        let size = task.memory_config.data + task.memory_config.stack;
+        let gap = gaps
+            .iter_mut()
+            .find(|(gap_base, gap_size)| align_16(*gap_base) - *gap_base + size <= *gap_size);
+        match gap {
+            Some((gap_base, gap_size)) => {
+                let base = align_16(*gap_base);
+                task.base_address = Some(base);
+                *gap_size -= base - *gap_base + size;
+                *gap_base = base + size;
+            }
+            None => {
+                base_address = align_16(base_address);
+                task.base_address = Some(base_address);
+                base_address += size;
+            }
+        }
+    }
+
+    // The crash record is kept at the top of RAM, where it isn't moved by
+    // changes to the memory layout of the kernel or tasks.
+    let noinit_size = NOINIT_SIZE * (xlen / 32);
+    let noinit_base = device_config.ram.base + device_config.ram.size - noinit_size;
+
+    let memory_used = base_address - device_config.ram.base + noinit_size;
     if memory_used > device_config.ram.size {
         panic!(
             "Out of memory, needed {memory_used} bytes but only {} bytes available",
@@ -232,88 +537,134 @@ pub fn build() {
 
     tasks.sort_unstable_by_key(|t| t.base_address.unwrap());
 
-    #[repr(u8)]
-    #[derive(Debug, Clone, Copy)]
-    #[allow(unused)]
-    enum PmpMode {
-        Off = 0,
-        TopOfRange = 1,
-        NaturallyAlignedFourByte = 2,
-        NaturallyAlignedPowerTwo = 3,
-    }
-
-    let pmp_addr = |base: u32, size: u32| match size {
-        0 => (PmpMode::Off, 0),
-        2 => panic!("A two byte PMP region is not permitted"),
-        4 => (PmpMode::NaturallyAlignedFourByte, base),
-        _ => (
-            PmpMode::NaturallyAlignedPowerTwo,
-            (base >> 2) | ((size >> 3) - 1),
-        ),
+    // The kernel requires that priorities are compact, so that every priority
+    // is less than the number of tasks. Only the relative order of priorities
+    // is significant, so each is replaced with its rank.
+    let mut priorities: Vec<u8> = tasks.iter().map(|t| t.priority).collect();
+    priorities.sort_unstable();
+    priorities.dedup();
+
+    // Task ids are assigned in the sorted order, so the masks can only be built
+    // once the tasks are sorted.
+    let task_mask = |names: &[String]| {
+        names.iter().fold(0u32, |mask, name| {
+            let idx = tasks.iter().position(|t| &t.name == name).unwrap();
+            mask | (1 << idx)
+        })
     };
 
-    let pmp_cfg = |l: bool, a: PmpMode, x: bool, w: bool, r: bool| {
-        ((r as u8) << 0) | ((w as u8) << 1) | ((x as u8) << 2) | ((a as u8) << 3) | ((l as u8) << 7)
-    };
+    // Each pmpcfg register holds the configuration byte of XLEN / 8 entries.
+    let entries_per_cfg = xlen as usize / 8;
 
     let task_tokens = tasks.iter().map(|task| {
-        if task.memory_regions.len() > 2 {
+        // Flash is readable and executable, task memory is readable and
+        // writable, and peripherals permit any access.
+        let mut pmp = Vec::new();
+        let mut push_range = |base: u32, size: u32, x: bool, w: bool, r: bool| {
+            for (mode, addr) in pmp_range(base, size) {
+                pmp.push((addr, pmp_cfg(false, mode, x, w, r)));
+            }
+        };
+
+        push_range(
+            device_config.flash.base,
+            device_config.flash.size,
+            true,
+            false,
+            true,
+        );
+        push_range(
+            task.base_address.unwrap(),
+            task.memory_config.stack + task.memory_config.data,
+            false,
+            true,
+            true,
+        );
+        for region in &task.memory_regions {
+            push_range(region.base, region.size, true, true, true);
+        }
+
+        if pmp.len() > pmp_entries {
             panic!(
-                "Only two MMIO regions supported per task, {} has {}",
+                "Task '{}' needs {} PMP entries but the {} device only has {pmp_entries}",
                 task.name,
-                task.memory_regions.len()
+                pmp.len(),
+                config.target.device
             );
         }
 
-        let mmio0 = task
-            .memory_regions
-            .get(0)
-            .map_or((0, 0), |r| (r.base, r.size));
-        let mmio1 = task
-            .memory_regions
-            .get(0)
-            .map_or((0, 0), |r| (r.base, r.size));
-
-        let pmp = [
-            pmp_addr(device_config.flash.base, device_config.flash.size),
-            pmp_addr(
-                task.base_address.unwrap(),
-                task.memory_config.stack + task.memory_config.data,
-            ),
-            pmp_addr(mmio0.0, mmio0.1),
-            pmp_addr(mmio1.0, mmio1.1),
-        ];
-
-        let pmp_cfg = ((pmp_cfg(false, pmp[0].0, true, false, true) as u32) << 0)
-            | ((pmp_cfg(false, pmp[1].0, false, true, true) as u32) << 8)
-            | ((pmp_cfg(false, pmp[2].0, true, true, true) as u32) << 16)
-            | ((pmp_cfg(false, pmp[3].0, true, true, true) as u32) << 24);
+        // Unused entries are left off.
+        pmp.resize(pmp_entries, (0, 0));
 
-        let pmp_addr = [pmp[0].1, pmp[1].1, pmp[2].1, pmp[3].1];
+        let pmp_addr = pmp.iter().map(|&(addr, _)| addr);
+        let pmp_cfg = pmp.chunks(entries_per_cfg).map(|entries| {
+            entries
+                .iter()
+                .enumerate()
+                .fold(0usize, |cfg, (i, &(_, entry))| {
+                    cfg | ((entry as usize) << (8 * i))
+                })
+        });
 
         let start_symbol = format!("_start.{}", task.name);
-        let priority = task.priority;
+        let stack_start_symbol = format!("_stack_start.{}", task.name);
+        let stack_end_symbol = format!("_stack_end.{}", task.name);
+        let priority = priorities.iter().position(|&p| p == task.priority).unwrap() as u8;
+        let uses = task_mask(&task.uses);
+        let notifies = task_mask(&task.notifies);
+        let watchdog = task.watchdog;
+        let hart = match task.hart {
+            Some(hart) => quote! { #hart },
+            None => quote! { ::kernel_types::task::ANY_HART },
+        };
 
         let mut flags = quote! { ::kernel_types::task::Flags::empty() };
         if task.boot {
             flags = quote! { #flags.union( ::kernel_types::task::Flags::BOOT ) };
         }
+        if task.supervisor {
+            flags = quote! { #flags.union( ::kernel_types::task::Flags::SUPERVISOR ) };
+        }
+        if task.critical {
+            flags = quote! { #flags.union( ::kernel_types::task::Flags::CRITICAL ) };
+        }
+        if task.introspect {
+            flags = quote! { #flags.union( ::kernel_types::task::Flags::INTROSPECT ) };
+        }
+        if task.fpu {
+            flags = quote! { #flags.union( ::kernel_types::task::Flags::FPU ) };
+        }
 
         quote! {
             ::kernel_types::task::TaskDescriptor {
                 init_pc: ::kernel_types::link_const!(#start_symbol),
+                stack_start: ::kernel_types::link_const!(#stack_start_symbol),
+                stack_end: ::kernel_types::link_const!(#stack_end_symbol),
                 priority: #priority,
                 flags: #flags,
+                uses: #uses,
+                notifies: #notifies,
+                watchdog_us: #watchdog,
+                hart: #hart,
                 arch: ::kernel_types::arch::riscv::ArchTaskDescriptor {
                     pmp_addr: [
                         #(#pmp_addr),*
                     ],
-                    pmp_cfg: #pmp_cfg,
+                    pmp_cfg: [
+                        #(#pmp_cfg),*
+                    ],
                 },
             }
         }
     });
 
+    let task_name_tokens = tasks.iter().map(|task| {
+        let name = &task.name;
+        quote! {
+            ::kernel_types::task::TaskName::new(#name)
+        }
+    });
+
     let task_id_tokens = tasks.iter().enumerate().map(|(idx, task)| {
         let symbol = format!("rtos.constant.{}.task_id", task.name);
         let idx = idx as u8;
@@ -331,6 +682,8 @@ pub fn build() {
     const TIME_US_PER_S: u64 = 1_000_000;
 
     let tick_frequency = config.target.clock;
+    let time_slice = config.kernel.time_slice;
+    let deep_sleep = config.kernel.deep_sleep;
     let us_per_tick = (((TIME_US_PER_S as u128) << 64) / (tick_frequency as u128)) as u64;
 
     let mut interrupt_descriptors = BTreeMap::new();
@@ -366,7 +719,14 @@ pub fn build() {
         )
     });
 
+    let critical_fault_action = match config.kernel.critical_fault {
+        CriticalFaultAction::Halt => quote! { ::kernel_types::task::CriticalFaultAction::Halt },
+        CriticalFaultAction::Reset => quote! { ::kernel_types::task::CriticalFaultAction::Reset },
+    };
+
     let task_count = config.tasks.len();
+    let num_tasks = task_count as u8;
+    let num_pmp_entries = device_config.pmp_entries as usize;
     let app_code = quote! {
         // This panic handler is unused and exists only to ensure that the app
         // crate builds successfully.
@@ -374,17 +734,38 @@ pub fn build() {
         #[cfg(target_os = "none")]
         fn panic(_: &::core::panic::PanicInfo) -> ! { loop { } }
 
+        // The task descriptors are built from the kernel_types dependency of
+        // the app, which must have the pmp_entries_N feature for the device.
+        const _: () = assert!(
+            ::kernel_types::arch::riscv::NUM_PMP_ENTRIES == #num_pmp_entries,
+            "kernel_types must be built with the pmp_entries feature matching the device"
+        );
+
         #[::rtos_macros::rtos_export]
         static TIME_US_PER_TICK: u64 = #us_per_tick;
 
         #[::rtos_macros::rtos_export]
         static TIME_TICK_FREQUENCY: u32 = #tick_frequency;
 
+        #[::rtos_macros::rtos_export]
+        static TIME_SLICE_US: u32 = #time_slice;
+
+        #[::rtos_macros::rtos_export]
+        static DEEP_SLEEP: bool = #deep_sleep;
+
+        #[::rtos_macros::rtos_export]
+        static CRITICAL_FAULT_ACTION: ::kernel_types::task::CriticalFaultAction = #critical_fault_action;
+
         #[::rtos_macros::rtos_export]
         static TASK_DESCRIPTOR_TABLE: [::kernel_types::task::TaskDescriptor; #task_count] = [
             #(#task_tokens),*
         ];
 
+        #[::rtos_macros::rtos_export]
+        static TASK_NAME_TABLE: [::kernel_types::task::TaskName; #task_count] = [
+            #(#task_name_tokens),*
+        ];
+
         #[::rtos_macros::rtos_export]
         static INTERRUPT_MIN: usize = #interrupt_min;
 
@@ -397,6 +778,10 @@ pub fn build() {
         ];
 
         #(#task_id_tokens)*
+
+        #[link_section = ".rtos.must_optimise"]
+        #[export_name = "rtos.constant.num_tasks"]
+        static NUM_TASKS: u8 = #num_tasks;
     };
 
     let app_code_syn_file: syn::File = syn::parse2(app_code.into()).unwrap();
@@ -414,13 +799,22 @@ pub fn build() {
 
     let mut feature_assertions = Vec::new();
     feature_assertions.push(format!("num_tasks_{}", config.tasks.len()));
+    feature_assertions.push(format!("num_timers_{}", config.kernel.timers));
+    feature_assertions.push(format!("num_harts_{}", config.kernel.harts));
     feature_assertions.push(format!("family_{}", device_config.family));
+    feature_assertions.push(format!("pmp_entries_{}", device_config.pmp_entries));
+
+    if config.tasks.values().any(|t| t.fpu) {
+        feature_assertions.push("fpu".to_string());
+    }
 
     let app_config = AppConfig {
         kernel: config.kernel,
         tasks,
         device: device_config,
         feature_assertions,
+        noinit_base,
+        noinit_size,
     };
 
     let linker_file_out_path = out_dir.join("build.ld");
@@ -477,5 +871,5 @@ pub fn build() {
         }
     }
 
-    println!("cargo:rustc-cfg=target_device=\"qemu-riscv32\"");
+    println!("cargo:rustc-cfg=target_device=\"qemu-riscv{xlen}\"");
 }
@@ -121,14 +121,58 @@ pub fn rtos_feature(input: TokenStream) -> TokenStream {
     .into()
 }
 
+// The same pattern as kernel_types::task::STACK_PAINT.
+const STACK_PAINT: u32 = 0xa5a5a5a5;
+
+struct TaskEntryAttr {
+    paint_stack: bool,
+}
+
+impl Parse for TaskEntryAttr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.is_empty() {
+            return Ok(Self { paint_stack: false });
+        }
+
+        let ident = input.parse::<Ident>()?;
+        if ident != "paint_stack" {
+            return Err(syn::Error::new(ident.span(), "expected `paint_stack`"));
+        }
+
+        Ok(Self { paint_stack: true })
+    }
+}
+
+// Define the entry point of a task. With #[rtos_task_entry(paint_stack)] the
+// stack is filled with STACK_PAINT before the task starts, so that the kernel
+// can report the stack high-water mark.
 #[proc_macro_attribute]
 pub fn rtos_task_entry(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let _ = parse_macro_input!(attr as EmptyAttr);
+    let TaskEntryAttr { paint_stack } = parse_macro_input!(attr as TaskEntryAttr);
     let mut entry_fn = parse_macro_input!(item as ItemFn);
     let fn_ident = entry_fn.sig.ident.clone();
     let inner_fn_ident = format_ident!("_inner_{}", fn_ident);
     entry_fn.sig.ident = inner_fn_ident.clone();
 
+    // Nothing has been pushed to the stack yet, so all of it can be painted.
+    let (paint_stack_asm, paint_stack_operand) = if paint_stack {
+        let li_pattern = format!("li a3, {STACK_PAINT:#x}");
+        let asm = quote! {
+            "la a0, {_stack_start}",
+            "la a1, {_stack_end}",
+            #li_pattern,
+            "bgeu a0, a1, 6f",
+            "5:",
+            "sw a3, (a0)",
+            "addi a0, a0, 4",
+            "bltu a0, a1, 5b",
+            "6:",
+        };
+        (asm, quote! { _stack_start = sym _stack_start, })
+    } else {
+        (quote! {}, quote! {})
+    };
+
     quote! {
         #[naked]
         #[export_name = "_start"]
@@ -143,6 +187,7 @@ pub fn rtos_task_entry(attr: TokenStream, item: TokenStream) -> TokenStream {
                 static _bss_start: ::core::ffi::c_void;
                 static _bss_end: ::core::ffi::c_void;
 
+                static _stack_start: ::core::ffi::c_void;
                 static _stack_end: ::core::ffi::c_void;
             }
 
@@ -150,6 +195,8 @@ pub fn rtos_task_entry(attr: TokenStream, item: TokenStream) -> TokenStream {
                 // Set the task stack pointer
                 "la sp, {_stack_end}",
 
+                #paint_stack_asm
+
                 // Load .data from flash
                 "la a0, {_data_start}",
                 "la a1, {_data_end}",
@@ -182,6 +229,7 @@ pub fn rtos_task_entry(attr: TokenStream, item: TokenStream) -> TokenStream {
                 _bss_start    = sym _bss_start,
                 _bss_end      = sym _bss_end,
                 _stack_end    = sym _stack_end,
+                #paint_stack_operand
                 task_main     = sym #inner_fn_ident,
                 options(noreturn))
         }
@@ -0,0 +1,106 @@
+// Report the stack high-water mark of each task against its configured stack
+// size, to help tune memory.stack in app.toml.
+//
+// Tasks must paint their stack with #[rtos_task_entry(paint_stack)]. Run the
+// app for a while, then dump RAM, for example on QEMU from the monitor:
+//
+//   (qemu) pmemsave 0x80000000 0x40000 ram.bin
+//
+// or from gdb:
+//
+//   (gdb) dump binary memory ram.bin 0x80000000 0x80040000
+//
+// Then run:
+//
+//   stack_report <elf> <ram.bin> <dump base address>
+
+use std::{collections::BTreeMap, env, fs, process};
+
+use object::{Object, ObjectSymbol};
+
+// Must match kernel_types::task::STACK_PAINT.
+const STACK_PAINT: u32 = 0xa5a5a5a5;
+
+fn parse_address(value: &str) -> u64 {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+
+    parsed.unwrap_or_else(|_| fail(&format!("invalid address {value}")))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("stack_report: {message}");
+    process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 4 {
+        fail("usage: stack_report <elf> <memory dump> <dump base address>");
+    }
+
+    let elf_data = fs::read(&args[1]).unwrap_or_else(|e| fail(&format!("{}: {e}", args[1])));
+    let dump = fs::read(&args[2]).unwrap_or_else(|e| fail(&format!("{}: {e}", args[2])));
+    let dump_base = parse_address(&args[3]);
+
+    let elf = object::File::parse(&*elf_data).unwrap_or_else(|e| fail(&format!("{e}")));
+
+    // The linker script defines _stack_start.<task> and _stack_end.<task> for
+    // every task.
+    let mut stacks: BTreeMap<String, (Option<u64>, Option<u64>)> = BTreeMap::new();
+    for symbol in elf.symbols() {
+        let name = match symbol.name() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+
+        if let Some(task) = name.strip_prefix("_stack_start.") {
+            stacks.entry(task.to_string()).or_default().0 = Some(symbol.address());
+        } else if let Some(task) = name.strip_prefix("_stack_end.") {
+            stacks.entry(task.to_string()).or_default().1 = Some(symbol.address());
+        }
+    }
+
+    // The kernel stack is not painted.
+    stacks.remove("kernel");
+
+    println!("{:<16} {:>8} {:>8} {:>6}", "task", "used", "size", "%");
+    for (task, bounds) in stacks {
+        let (start, end) = match bounds {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+
+        let offset = match start.checked_sub(dump_base) {
+            Some(offset) if end - dump_base <= dump.len() as u64 => offset as usize,
+            _ => fail(&format!("stack of {task} is not within the memory dump")),
+        };
+
+        let size = (end - start) as usize;
+        let unused = dump[offset..offset + size]
+            .chunks_exact(4)
+            .take_while(|word| u32::from_le_bytes((*word).try_into().unwrap()) == STACK_PAINT)
+            .count()
+            * 4;
+        let used = size - unused;
+
+        // A task which overflows its stack faults, so a stack which is
+        // entirely used was most likely never painted.
+        let note = if unused == 0 {
+            "  (not painted, or full)"
+        } else {
+            ""
+        };
+
+        println!(
+            "{:<16} {:>8} {:>8} {:>5}%{}",
+            task,
+            used,
+            size,
+            used * 100 / size.max(1),
+            note
+        );
+    }
+}
@@ -0,0 +1,346 @@
+// Convert the kernel trace buffer to Chrome trace JSON, which can be opened in
+// Perfetto (https://ui.perfetto.dev).
+//
+// The kernel must be built with the trace feature. The buffer is read from a
+// dump of RAM, for example on QEMU from the monitor:
+//
+//   (qemu) pmemsave 0x80000000 0x40000 ram.bin
+//
+// or from gdb:
+//
+//   (gdb) dump binary memory ram.bin 0x80000000 0x80040000
+//
+// Then run:
+//
+//   trace_export <elf> <ram.bin> <dump base address> > trace.json
+
+use std::{collections::HashMap, env, fs, process};
+
+use object::{Object, ObjectSection, ObjectSymbol};
+use serde_json::{json, Value};
+
+// Must match sys/kernel/src/trace.rs.
+const TRACE_MAGIC: u32 = 0x43525452;
+const HEADER_SIZE: usize = 16;
+const EVENT_SIZE: usize = 16;
+
+const EVENT_SCHEDULE: u8 = 0;
+const EVENT_SWITCH: u8 = 1;
+const EVENT_SYSCALL: u8 = 2;
+const EVENT_INTERRUPT: u8 = 3;
+const EVENT_TIMER: u8 = 4;
+const EVENT_IDLE: u8 = 5;
+const EVENT_FAULT: u8 = 6;
+
+// Must match the order of SysCallId in sys/kernel_types.
+const SYSCALL_NAMES: &[&str] = &[
+    "panic",
+    "receive",
+    "send",
+    "call",
+    "notify",
+    "set_timer",
+    "interrupt_control",
+    "restart_task",
+    "read_fault",
+    "call_timeout",
+    "borrow_info",
+    "borrow_read",
+    "borrow_write",
+    "yield",
+    "get_time",
+    "set_timer_absolute",
+    "task_info",
+    "read_crash",
+    "heartbeat",
+    "faulted_tasks",
+    "timer_missed",
+];
+const SYSCALL_SEND: u8 = 2;
+const SYSCALL_CALL: u8 = 3;
+const SYSCALL_CALL_TIMEOUT: u8 = 9;
+
+// The thread ids used for the kernel itself, tasks use their task id.
+const KERNEL_TID: u32 = 1000;
+const IDLE_TID: u32 = 1001;
+
+// Each hart is shown as a process with its hart id, outstanding calls are shown
+// on a separate process.
+const CALLS_PID: u32 = 100;
+
+struct Event {
+    timestamp: u64,
+    kind: u8,
+    task: u8,
+    hart: u8,
+    arg: u32,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn parse_address(value: &str) -> u64 {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+
+    parsed.unwrap_or_else(|_| fail(&format!("invalid address {value}")))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("trace_export: {message}");
+    process::exit(1);
+}
+
+// Find a symbol by name, allowing for the suffix added when the kernel and
+// tasks are linked into the app.
+fn find_symbol<'a>(elf: &'a object::File<'a>, name: &str) -> Option<object::Symbol<'a, 'a>> {
+    elf.symbols().find(|symbol| {
+        symbol
+            .name()
+            .is_ok_and(|n| n == name || n.strip_prefix(name).is_some_and(|s| s.starts_with('.')))
+    })
+}
+
+// Read the task names from the name table in the ELF, if it is present.
+fn read_task_names(elf: &object::File) -> Vec<String> {
+    let symbol = match find_symbol(elf, "rtos.TASK_NAME_TABLE") {
+        Some(symbol) => symbol,
+        None => return Vec::new(),
+    };
+
+    let data = elf
+        .sections()
+        .filter(|section| {
+            section.address() <= symbol.address()
+                && symbol.address() + symbol.size() <= section.address() + section.size()
+        })
+        .find_map(|section| section.data().ok().map(|data| (section.address(), data)));
+
+    match data {
+        Some((base, data)) => {
+            let start = (symbol.address() - base) as usize;
+            data[start..start + symbol.size() as usize]
+                .chunks_exact(16)
+                .map(|name| {
+                    let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+                    String::from_utf8_lossy(&name[..len]).into_owned()
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    }
+}
+
+// Read the events in the ring buffer, oldest first.
+fn read_events(buffer: &[u8]) -> (u32, Vec<Event>) {
+    if read_u32(buffer, 0) != TRACE_MAGIC {
+        fail("trace buffer is not initialized, was an event recorded?");
+    }
+
+    let tick_frequency = read_u32(buffer, 4);
+    let capacity = read_u32(buffer, 8);
+    let head = read_u32(buffer, 12);
+
+    let events = (head.saturating_sub(capacity)..head)
+        .map(|i| {
+            let offset = HEADER_SIZE + (i % capacity) as usize * EVENT_SIZE;
+            Event {
+                timestamp: read_u64(buffer, offset),
+                kind: buffer[offset + 8],
+                task: buffer[offset + 9],
+                hart: buffer[offset + 10],
+                arg: read_u32(buffer, offset + 12),
+            }
+        })
+        .collect();
+
+    (tick_frequency, events)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 4 {
+        fail("usage: trace_export <elf> <memory dump> <dump base address>");
+    }
+
+    let elf_data = fs::read(&args[1]).unwrap_or_else(|e| fail(&format!("{}: {e}", args[1])));
+    let dump = fs::read(&args[2]).unwrap_or_else(|e| fail(&format!("{}: {e}", args[2])));
+    let dump_base = parse_address(&args[3]);
+
+    let elf = object::File::parse(&*elf_data).unwrap_or_else(|e| fail(&format!("{e}")));
+    let symbol = find_symbol(&elf, "TRACE_BUFFER")
+        .unwrap_or_else(|| fail("TRACE_BUFFER not found, is the trace feature enabled?"));
+
+    let start = symbol
+        .address()
+        .checked_sub(dump_base)
+        .map(|offset| offset as usize)
+        .filter(|&offset| offset + symbol.size() as usize <= dump.len())
+        .unwrap_or_else(|| fail("TRACE_BUFFER is not within the memory dump"));
+    let buffer = &dump[start..start + symbol.size() as usize];
+
+    let (tick_frequency, events) = read_events(buffer);
+    let names = read_task_names(&elf);
+
+    let trace = convert(tick_frequency, &events, &names);
+    println!("{}", serde_json::to_string_pretty(&trace).unwrap());
+}
+
+// End the slice that is currently running on a hart, if any.
+fn end_running(trace: &mut Vec<Value>, running: &mut HashMap<u8, (u32, f64)>, hart: u8, ts: f64) {
+    if let Some((tid, start)) = running.remove(&hart) {
+        let name = if tid == IDLE_TID { "idle" } else { "running" };
+        trace.push(json!({
+            "name": name, "ph": "X", "pid": hart, "tid": tid, "ts": start, "dur": ts - start,
+        }));
+    }
+}
+
+fn convert(tick_frequency: u32, events: &[Event], names: &[String]) -> Value {
+    let to_us = |ticks: u64| ticks as f64 * 1_000_000.0 / tick_frequency as f64;
+    let task_name = |task: u8| match names.get(task as usize) {
+        Some(name) => name.clone(),
+        None => format!("task {task}"),
+    };
+
+    let mut trace = Vec::new();
+    let mut thread_names = HashMap::new();
+    thread_names.insert(KERNEL_TID, "kernel".to_string());
+    thread_names.insert(IDLE_TID, "idle".to_string());
+    for (tid, name) in names.iter().enumerate() {
+        thread_names.insert(tid as u32, name.clone());
+    }
+
+    // The thread and start time of the slice that is currently running on
+    // each hart.
+    let mut running: HashMap<u8, (u32, f64)> = HashMap::new();
+    let mut harts = vec![0];
+    // Calls awaiting a reply, keyed by the caller and callee, with the time of
+    // the call and the id of the flow between them.
+    let mut calls: HashMap<(u8, u8), (f64, u64)> = HashMap::new();
+    let mut next_flow = 0;
+
+    for event in events {
+        let ts = to_us(event.timestamp);
+        let tid = event.task as u32;
+        let pid = event.hart;
+        if !harts.contains(&pid) {
+            harts.push(pid);
+        }
+
+        match event.kind {
+            EVENT_SWITCH => {
+                end_running(&mut trace, &mut running, pid, ts);
+                running.insert(pid, (tid, ts));
+                thread_names.insert(tid, task_name(event.task));
+            }
+            EVENT_IDLE => {
+                end_running(&mut trace, &mut running, pid, ts);
+                running.insert(pid, (IDLE_TID, ts));
+            }
+            EVENT_SYSCALL => {
+                let id = (event.arg & 0xff) as u8;
+                let target = ((event.arg >> 8) & 0xff) as u8;
+                let name = SYSCALL_NAMES.get(id as usize).copied().unwrap_or("unknown");
+
+                trace.push(json!({
+                    "name": name, "ph": "i", "s": "t", "pid": pid, "tid": tid, "ts": ts,
+                    "args": { "target": target },
+                }));
+
+                match id {
+                    SYSCALL_CALL | SYSCALL_CALL_TIMEOUT => {
+                        trace.push(json!({
+                            "name": "ipc", "ph": "s", "id": next_flow, "pid": pid, "tid": tid,
+                            "ts": ts,
+                        }));
+                        calls.insert((event.task, target), (ts, next_flow));
+                        next_flow += 1;
+                    }
+                    SYSCALL_SEND => {
+                        // A send from the callee to a caller is the reply.
+                        if let Some((start, flow)) = calls.remove(&(target, event.task)) {
+                            trace.push(json!({
+                                "name": "ipc", "ph": "f", "bp": "e", "id": flow, "pid": pid,
+                                "tid": tid, "ts": ts,
+                            }));
+                            trace.push(json!({
+                                "name": format!("call {}", task_name(event.task)), "ph": "X",
+                                "pid": CALLS_PID, "tid": target as u32, "ts": start, "dur": ts - start,
+                            }));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            EVENT_INTERRUPT => trace.push(json!({
+                "name": format!("irq {}", event.arg), "ph": "i", "s": "t", "pid": pid,
+                "tid": tid, "ts": ts,
+            })),
+            EVENT_TIMER => {
+                let (name, tid) = match event.arg {
+                    0 => ("time slice".to_string(), KERNEL_TID),
+                    1 => ("call timeout".to_string(), tid),
+                    2 => ("watchdog".to_string(), tid),
+                    slot => (format!("timer {}", slot - 3), tid),
+                };
+                trace.push(json!({
+                    "name": name, "ph": "i", "s": "t", "pid": pid, "tid": tid, "ts": ts,
+                }));
+            }
+            EVENT_FAULT => trace.push(json!({
+                "name": "fault", "ph": "i", "s": "p", "pid": pid, "tid": tid, "ts": ts,
+                "args": { "mcause": format!("{:#x}", event.arg) },
+            })),
+            EVENT_SCHEDULE => {
+                let schedule = match event.arg {
+                    0 => "same",
+                    1 => "exactly",
+                    _ => "other",
+                };
+                trace.push(json!({
+                    "name": "schedule", "ph": "i", "s": "t", "pid": pid, "tid": KERNEL_TID,
+                    "ts": ts, "args": { "task": event.task, "schedule": schedule },
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(last) = events.last() {
+        for &hart in &harts {
+            end_running(&mut trace, &mut running, hart, to_us(last.timestamp));
+        }
+    }
+
+    // Name the processes and threads, outstanding calls are shown on a
+    // separate process so that they don't overlap the running slices.
+    let mut pids: Vec<u32> = harts.iter().map(|&hart| hart as u32).collect();
+    for &pid in &pids {
+        trace.push(json!({
+            "name": "process_name", "ph": "M", "pid": pid,
+            "args": { "name": format!("hart {pid}") },
+        }));
+    }
+    trace.push(json!({
+        "name": "process_name", "ph": "M", "pid": CALLS_PID, "args": { "name": "calls" },
+    }));
+    pids.push(CALLS_PID);
+    for (tid, name) in &thread_names {
+        for &pid in &pids {
+            trace.push(json!({
+                "name": "thread_name", "ph": "M", "pid": pid, "tid": tid,
+                "args": { "name": name },
+            }));
+        }
+    }
+
+    json!({ "traceEvents": trace, "displayTimeUnit": "ns" })
+}